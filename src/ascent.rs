@@ -0,0 +1,116 @@
+//! Gradient ascent (or descent) on noise, to find the local extremum that a
+//! point flows to and partition the plane into the basins of these extrema.
+//!
+//! The fields are functions that give a value along with its gradient, such
+//! as `octaves_noise_with_gradient` with the noise and the channels bound.
+
+/// Which kind of local extremum to go to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Extremum {
+	Maximum,
+	Minimum,
+}
+
+/// Settings of a gradient ascent, with distances in the coordinates
+/// that the field takes.
+#[derive(Clone, Copy, Debug)]
+pub struct GradientAscent {
+	pub extremum: Extremum,
+	/// Length of the first step, and of the longest steps. Long steps can
+	/// jump from a basin to another, so it should be well below the
+	/// distance between two extrema.
+	pub max_step: f32,
+	/// The ascent has converged when a step that does not improve the value
+	/// gets shorter than this.
+	pub tolerance: f32,
+	/// The ascent stops there even if it has not converged.
+	pub max_iterations: usize,
+	/// Size of the grid cells that the extrema are rounded to for
+	/// `Climb::basin`, it should be much bigger than the tolerance but smaller
+	/// than the distance between two extrema.
+	pub basin_resolution: f32,
+}
+
+/// Where a gradient ascent ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Climb<const D: usize> {
+	pub point: [f32; D],
+	pub value: f32,
+	/// How many times the field was evaluated after the start.
+	pub steps: usize,
+	/// False if the ascent ran out of iterations.
+	pub converged: bool,
+	/// The reached point rounded to `GradientAscent::basin_resolution`, equal
+	/// for the ascents that reach the same extremum. An extremum that lies
+	/// within the tolerance of a rounding boundary can have two ids.
+	pub basin: [i64; D],
+}
+
+impl GradientAscent {
+	/// Settings for noise sampled with features about 1 apart.
+	pub fn new(extremum: Extremum) -> GradientAscent {
+		GradientAscent {
+			extremum,
+			max_step: 0.03,
+			tolerance: 1e-5,
+			max_iterations: 200,
+			basin_resolution: 0.01,
+		}
+	}
+
+	/// Follows the gradient given by `field` from `start`. Each step goes as
+	/// far as the current step length along the gradient, the length grows
+	/// after a step that improves the value without going past the extremum
+	/// along the step, and is halved (and the step retried) otherwise.
+	pub fn climb<const D: usize>(
+		&self,
+		field: &impl Fn(&[f32; D]) -> (f32, [f32; D]),
+		start: &[f32; D],
+	) -> Climb<D> {
+		let sign = match self.extremum {
+			Extremum::Maximum => 1.0,
+			Extremum::Minimum => -1.0,
+		};
+		let mut point = *start;
+		let (mut value, mut gradient) = field(&point);
+		let mut step = self.max_step;
+		let mut steps = 0;
+		let mut converged = false;
+		while steps < self.max_iterations {
+			let length = f32::sqrt(gradient.iter().map(|g| g * g).sum());
+			if length == 0.0 {
+				// Exactly on the extremum (or on a plateau).
+				converged = true;
+				break;
+			}
+			let mut candidate = point;
+			for (x, g) in candidate.iter_mut().zip(gradient) {
+				*x += g / length * step * sign;
+			}
+			let (candidate_value, candidate_gradient) = field(&candidate);
+			steps += 1;
+			let improved = (candidate_value - value) * sign > 0.0;
+			// Near the extremum the values get too close to compare, but the
+			// gradient still tells whether the step went past it.
+			let short = step < self.basin_resolution;
+			let before_extremum = candidate_gradient
+				.iter()
+				.zip(gradient)
+				.map(|(a, b)| a * b)
+				.sum::<f32>()
+				> 0.0;
+			if before_extremum && (improved || short) {
+				(point, value, gradient) = (candidate, candidate_value, candidate_gradient);
+				step = f32::min(step * 1.5, self.max_step);
+			} else {
+				step *= 0.5;
+				if step < self.tolerance {
+					converged = true;
+					break;
+				}
+			}
+		}
+		let basin = point.map(|x| f32::round(x / self.basin_resolution) as i64);
+		Climb { point, value, steps, converged, basin }
+	}
+}
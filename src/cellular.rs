@@ -0,0 +1,151 @@
+//! Cellular (Worley) noise, made of distances to pseudo-random feature points
+//! scattered one per lattice cell.
+
+use crate::{raw_noise_node_parts, split_coords, Coord, LatticeHash, Noise};
+use serde::{Deserialize, Serialize};
+
+/// How distances to the feature points are measured.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+	/// Round cells.
+	#[default]
+	Euclidean,
+	/// Diamond-ish cells.
+	Manhattan,
+	/// Square-ish cells.
+	Chebyshev,
+}
+
+impl DistanceMetric {
+	pub fn distance<const D: usize>(self, a: &[f32; D], b: &[f32; D]) -> f32 {
+		let deltas = a.iter().zip(b).map(|(a, b)| f32::abs(a - b));
+		match self {
+			DistanceMetric::Euclidean => f32::sqrt(deltas.map(|delta| delta * delta).sum()),
+			DistanceMetric::Manhattan => deltas.sum(),
+			DistanceMetric::Chebyshev => deltas.fold(0.0, f32::max),
+		}
+	}
+}
+
+/// What is known around a point about the closest feature points.
+#[derive(Clone, Copy, Debug)]
+pub struct Cellular {
+	/// Distance to the nearest feature point.
+	pub f1: f32,
+	/// Distance to the second nearest feature point.
+	pub f2: f32,
+	/// Pseudo-random identifier of the cell of the nearest feature point,
+	/// good for picking a color per cell.
+	pub cell_id: u32,
+}
+
+impl Cellular {
+	/// Close to zero near the borders between cells, good for cracks.
+	pub fn f2_minus_f1(&self) -> f32 {
+		self.f2 - self.f1
+	}
+}
+
+/// The distances of `Cellular` that make a noise function (see `noise_fn::cells`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellularValue {
+	F1,
+	F2,
+	F2MinusF1,
+}
+
+impl CellularValue {
+	pub fn of(self, cells: &Cellular) -> f32 {
+		match self {
+			CellularValue::F1 => cells.f1,
+			CellularValue::F2 => cells.f2,
+			CellularValue::F2MinusF1 => cells.f2_minus_f1(),
+		}
+	}
+}
+
+/// Position of the feature point of the given lattice cell,
+/// relative to the cell that is `offset` cells before it.
+pub(crate) fn feature_point<H: LatticeHash, const D: usize>(
+	noise: &Noise<H>,
+	channels: &[i32],
+	cell: &[i64; D],
+	offset: &[i64; D],
+) -> [f32; D] {
+	let wrapped_cell = noise.wrap_lattice_coords(cell);
+	let mut point = [0.0; D];
+	for (axis, coord) in point.iter_mut().enumerate() {
+		// Negative axis markers keep this apart from the lattice gradients.
+		let jitter = raw_noise_node_parts(noise, &[channels, &wrapped_cell, &[-1 - axis as i32]]);
+		*coord = offset[axis] as f32 + jitter;
+	}
+	point
+}
+
+pub fn cellular_noise<H: LatticeHash, C: Coord, const D: usize>(
+	noise: &Noise<H>,
+	xs: &[C; D],
+	channels: &[i32],
+	metric: DistanceMetric,
+) -> Cellular {
+	// Distances are measured from the cell that contains the point,
+	// so that they keep their precision far from the origin.
+	let (center_cell, x_fracts) = split_coords(xs);
+	let mut f1 = f32::INFINITY;
+	let mut f2 = f32::INFINITY;
+	let mut nearest_cell = center_cell;
+	// Feature points can be anywhere in their cell, so the two nearest ones
+	// can be two cells away: the 5^D cells around the point are searched,
+	// the 3^D closest first, so that most of the others can be skipped.
+	for ring in 1..=2 {
+		for neighbor in 0..5usize.pow(D as u32) {
+			let mut cell = center_cell;
+			let mut offset = [0; D];
+			let mut digits = neighbor;
+			for axis in 0..D {
+				offset[axis] = (digits % 5) as i64 - 2;
+				cell[axis] = cell[axis].wrapping_add(offset[axis]);
+				digits /= 5;
+			}
+			if offset
+				.iter()
+				.map(|offset| offset.abs())
+				.max()
+				.unwrap_or(0)
+				.max(1) != ring
+			{
+				continue;
+			}
+			// No point of the cell is closer than this on a single axis,
+			// which is a lower bound of the distance with every metric.
+			let gap = (0..D)
+				.map(|axis| match offset[axis] {
+					0 => 0.0,
+					offset if offset > 0 => offset as f32 - x_fracts[axis],
+					offset => x_fracts[axis] - (offset + 1) as f32,
+				})
+				.fold(0.0, f32::max);
+			if gap >= f2 {
+				continue;
+			}
+			let point = feature_point(noise, channels, &cell, &offset);
+			let distance = metric.distance(&x_fracts, &point);
+			if distance < f1 {
+				f2 = f1;
+				f1 = distance;
+				nearest_cell = cell;
+			} else if distance < f2 {
+				f2 = distance;
+			}
+		}
+	}
+	let nearest_cell = noise.wrap_lattice_coords(&nearest_cell);
+	let id_value = raw_noise_node_parts(noise, &[channels, &nearest_cell]);
+	Cellular {
+		f1,
+		f2,
+		cell_id: (id_value * (1 << 24) as f32) as u32,
+	}
+}
@@ -0,0 +1,170 @@
+//! Curl noise: flow fields derived from octave noise that have no divergence,
+//! so that points pushed along them swirl around without gathering in sinks
+//! or spreading from sources like they do along noise-derived angles.
+//!
+//! In 2D the flow is the curl of one noise used as a scalar potential, in 3D
+//! it is the curl of three noises used as the components of a vector potential.
+//! Points are pushed along a flow with `advect` and `trajectory`, or with
+//! `Advection`, which warps a noise function along a flow.
+
+use crate::warp::Displacement;
+use crate::{octaves_noise_with_gradient, LatticeHash, LegacyHash, Noise};
+
+/// A velocity for every point in D dimensions.
+pub trait VectorField<const D: usize> {
+	fn velocity(&self, xs: &[f32; D]) -> [f32; D];
+}
+
+/// Any function from a point to a vector is a vector field.
+impl<F: Fn(&[f32; D]) -> [f32; D], const D: usize> VectorField<D> for F {
+	fn velocity(&self, xs: &[f32; D]) -> [f32; D] {
+		self(xs)
+	}
+}
+
+/// See `curl_2d`.
+#[derive(Clone, Copy, Debug)]
+pub struct Curl2d<'a, H: LatticeHash = LegacyHash> {
+	pub noise: &'a Noise<H>,
+	pub octave_count: i32,
+	pub channels: &'a [i32],
+	/// The potential is sampled at `xs * frequency`.
+	pub frequency: f32,
+}
+
+/// The 2D curl of `octaves_noise` on `channels`, the velocity being
+/// the gradient of the noise turned by a quarter turn clockwise, so that the
+/// flow follows the contour lines of the noise.
+pub fn curl_2d<'a, H: LatticeHash>(
+	noise: &'a Noise<H>,
+	octave_count: i32,
+	channels: &'a [i32],
+) -> Curl2d<'a, H> {
+	Curl2d { noise, octave_count, channels, frequency: 1.0 }
+}
+
+impl<H: LatticeHash> Curl2d<'_, H> {
+	/// Same as `NoiseFn::scale` on the potential, the velocities are scaled
+	/// too so that the flow stays the curl of the scaled potential.
+	pub fn scale(self, factor: f32) -> Self {
+		Curl2d { frequency: self.frequency * factor, ..self }
+	}
+}
+
+impl<H: LatticeHash> VectorField<2> for Curl2d<'_, H> {
+	fn velocity(&self, xs: &[f32; 2]) -> [f32; 2] {
+		let xs = xs.map(|x| x * self.frequency);
+		let (_, gradient) =
+			octaves_noise_with_gradient(self.noise, self.octave_count, &xs, self.channels);
+		[gradient[1] * self.frequency, -gradient[0] * self.frequency]
+	}
+}
+
+/// See `curl_3d`.
+#[derive(Clone, Copy, Debug)]
+pub struct Curl3d<'a, H: LatticeHash = LegacyHash> {
+	pub noise: &'a Noise<H>,
+	pub octave_count: i32,
+	/// The channels of the three components of the potential.
+	pub channels: [&'a [i32]; 3],
+	/// The potential is sampled at `xs * frequency`.
+	pub frequency: f32,
+}
+
+/// The 3D curl of a vector potential whose components are `octaves_noise`
+/// on the three `channels`.
+pub fn curl_3d<'a, H: LatticeHash>(
+	noise: &'a Noise<H>,
+	octave_count: i32,
+	channels: [&'a [i32]; 3],
+) -> Curl3d<'a, H> {
+	Curl3d { noise, octave_count, channels, frequency: 1.0 }
+}
+
+impl<H: LatticeHash> Curl3d<'_, H> {
+	/// See `Curl2d::scale`.
+	pub fn scale(self, factor: f32) -> Self {
+		Curl3d { frequency: self.frequency * factor, ..self }
+	}
+}
+
+impl<H: LatticeHash> VectorField<3> for Curl3d<'_, H> {
+	fn velocity(&self, xs: &[f32; 3]) -> [f32; 3] {
+		let xs = xs.map(|x| x * self.frequency);
+		let [a, b, c] = self.channels.map(|channels| {
+			octaves_noise_with_gradient(self.noise, self.octave_count, &xs, channels).1
+		});
+		[
+			(c[1] - b[2]) * self.frequency,
+			(a[2] - c[0]) * self.frequency,
+			(b[0] - a[1]) * self.frequency,
+		]
+	}
+}
+
+/// One midpoint (second order Runge-Kutta) step of duration `step`,
+/// which follows the curves of a flow much better than a plain Euler step.
+fn advection_step<V: VectorField<D>, const D: usize>(
+	field: &V,
+	xs: &[f32; D],
+	step: f32,
+) -> [f32; D] {
+	let velocity = field.velocity(xs);
+	let mut middle = *xs;
+	for (x, v) in middle.iter_mut().zip(velocity) {
+		*x += v * step * 0.5;
+	}
+	let velocity = field.velocity(&middle);
+	let mut xs = *xs;
+	for (x, v) in xs.iter_mut().zip(velocity) {
+		*x += v * step;
+	}
+	xs
+}
+
+/// The positions of a particle that starts at `xs` and moves along `field`
+/// for `steps` steps of duration `step` (negative to go back in time),
+/// starting with `xs` itself.
+pub fn trajectory<'a, V: VectorField<D>, const D: usize>(
+	field: &'a V,
+	xs: &[f32; D],
+	step: f32,
+	steps: usize,
+) -> impl Iterator<Item = [f32; D]> + 'a {
+	std::iter::successors(Some(*xs), move |xs| Some(advection_step(field, xs, step))).take(steps + 1)
+}
+
+/// Where a particle that starts at `xs` ends after the steps of `trajectory`.
+pub fn advect<V: VectorField<D>, const D: usize>(
+	field: &V,
+	xs: &[f32; D],
+	step: f32,
+	steps: usize,
+) -> [f32; D] {
+	(0..steps).fold(*xs, |xs, _| advection_step(field, &xs, step))
+}
+
+/// Moves points to where `advect` takes them, for `NoiseFn::warp_with`.
+/// With a negative step, a warped noise looks like the noise carried
+/// forward by the flow.
+#[derive(Clone, Copy, Debug)]
+pub struct Advection<V> {
+	pub field: V,
+	pub step: f32,
+	pub steps: usize,
+}
+
+impl<V: VectorField<D>, const D: usize> Displacement<D> for Advection<V> {
+	fn offset(&self, xs: &[f32; D]) -> [f32; D] {
+		let mut offset = advect(&self.field, xs, self.step, self.steps);
+		offset
+			.iter_mut()
+			.zip(xs)
+			.for_each(|(offset, x)| *offset -= x);
+		offset
+	}
+
+	fn displace(&self, xs: &[f32; D]) -> [f32; D] {
+		advect(&self.field, xs, self.step, self.steps)
+	}
+}
@@ -0,0 +1,234 @@
+//! Erosion of heightfields, which carves the valleys and the drainage
+//! networks that raw noise lacks.
+//!
+//! Hydraulic erosion follows drops of rain down the terrain, each picking up
+//! sediment where it speeds up and dropping it where it slows down. Thermal
+//! erosion makes the slopes that are steeper than the talus angle crumble onto
+//! their lower neighbors. Both only depend on their settings and the seed, so
+//! an eroded terrain is as reproducible as the noise it comes from.
+
+use crate::hash::{LatticeHash, SplitMixHash};
+use crate::heightfield::Heightfield;
+
+/// Settings of droplet-based hydraulic erosion, with distances in pixels.
+#[derive(Clone, Debug)]
+pub struct HydraulicErosion {
+	/// Number of droplets, one after the other.
+	pub droplets: usize,
+	/// The droplets start at pseudo-random places derived from the seed.
+	pub seed: u64,
+	/// Number of steps a droplet moves (of one pixel each) before it is dropped.
+	pub lifetime: usize,
+	/// Amount of water in a new droplet.
+	pub rain: f32,
+	/// Part of its water a droplet loses at each step.
+	pub evaporation: f32,
+	/// How much sediment a droplet can carry,
+	/// per unit of speed, water and downhill slope.
+	pub sediment_capacity: f32,
+	/// The slope below which a droplet carries sediment as if it was this
+	/// slope, so that the flat parts still get some erosion.
+	pub min_slope: f32,
+	/// Part of the missing sediment (up to its capacity) picked up at each step.
+	pub erosion_rate: f32,
+	/// Part of the excess sediment (beyond its capacity) dropped at each step.
+	pub deposition_rate: f32,
+	/// Part of its direction a droplet keeps from a step to the next instead
+	/// of following the slope.
+	pub inertia: f32,
+	pub gravity: f32,
+	/// Radius of the area that a droplet erodes around itself.
+	pub radius: u32,
+}
+
+impl HydraulicErosion {
+	pub fn new(droplets: usize, seed: u64) -> HydraulicErosion {
+		HydraulicErosion {
+			droplets,
+			seed,
+			lifetime: 30,
+			rain: 1.0,
+			evaporation: 0.01,
+			sediment_capacity: 4.0,
+			min_slope: 0.01,
+			erosion_rate: 0.3,
+			deposition_rate: 0.3,
+			inertia: 0.05,
+			gravity: 4.0,
+			radius: 3,
+		}
+	}
+
+	pub fn apply(&self, field: &mut Heightfield) {
+		if field.side < 2 {
+			return;
+		}
+		let brush = self.brush();
+		let limit = (field.side - 1) as f32;
+		for droplet in 0..self.droplets {
+			let droplet = droplet as u64;
+			let start = [0, 1].map(|axis| {
+				// The top 24 bits make an exact float in [0, 1[.
+				let hash = SplitMixHash.hash(self.seed, [droplet as i32, (droplet >> 32) as i32, axis]);
+				(hash >> 40) as f32 / (1u64 << 24) as f32 * limit
+			});
+			self.run_droplet(field, &brush, start);
+		}
+	}
+
+	/// The weights of the pixels eroded around a droplet, by offset,
+	/// falling off linearly with the distance and summing to 1.
+	fn brush(&self) -> Vec<(i64, i64, f32)> {
+		let radius = self.radius as i64;
+		let mut brush = Vec::new();
+		for dy in -radius..=radius {
+			for dx in -radius..=radius {
+				let weight = self.radius as f32 - f32::hypot(dx as f32, dy as f32);
+				if weight > 0.0 {
+					brush.push((dx, dy, weight));
+				}
+			}
+		}
+		let sum: f32 = brush.iter().map(|(_, _, weight)| weight).sum();
+		brush.iter_mut().for_each(|(_, _, weight)| *weight /= sum);
+		brush
+	}
+
+	fn run_droplet(&self, field: &mut Heightfield, brush: &[(i64, i64, f32)], start: [f32; 2]) {
+		let limit = (field.side - 1) as f32;
+		let mut position = start;
+		let mut direction = [0.0f32; 2];
+		let mut speed = 1.0;
+		let mut water = self.rain;
+		let mut sediment = 0.0;
+		for _ in 0..self.lifetime {
+			let (height, gradient) = field.interpolate(position[0], position[1]);
+			for (direction, slope) in direction.iter_mut().zip(gradient) {
+				*direction = *direction * self.inertia - slope * (1.0 - self.inertia);
+			}
+			let length = f32::hypot(direction[0], direction[1]);
+			if length == 0.0 {
+				// In a perfectly flat spot the droplet has nowhere to go.
+				break;
+			}
+			direction = direction.map(|d| d / length);
+			let next = [position[0] + direction[0], position[1] + direction[1]];
+			if !next.iter().all(|x| (0.0..limit).contains(x)) {
+				break;
+			}
+			let height_change = field.interpolate(next[0], next[1]).0 - height;
+			let capacity =
+				f32::max(-height_change, self.min_slope) * speed * water * self.sediment_capacity;
+			if height_change > 0.0 || sediment > capacity {
+				// Uphill it fills the pit it comes from, if it can.
+				let deposit = if height_change > 0.0 {
+					f32::min(height_change, sediment)
+				} else {
+					(sediment - capacity) * self.deposition_rate
+				};
+				sediment -= deposit;
+				deposit_bilinearly(field, position, deposit);
+			} else {
+				// Never digs deeper than where it goes, which would make a pit.
+				let erosion = f32::min((capacity - sediment) * self.erosion_rate, -height_change);
+				sediment += erode_brush(field, brush, position, erosion);
+			}
+			speed = f32::sqrt(f32::max(speed * speed - height_change * self.gravity, 0.0));
+			water *= 1.0 - self.evaporation;
+			position = next;
+		}
+	}
+}
+
+/// Adds `amount` to the four pixels around `position`, with bilinear weights.
+fn deposit_bilinearly(field: &mut Heightfield, position: [f32; 2], amount: f32) {
+	let (px, py) = (position[0] as u32, position[1] as u32);
+	let (u, v) = (position[0] - px as f32, position[1] - py as f32);
+	let side = field.side;
+	let corners = [
+		(px, py, (1.0 - u) * (1.0 - v)),
+		(px + 1, py, u * (1.0 - v)),
+		(px, py + 1, (1.0 - u) * v),
+		(px + 1, py + 1, u * v),
+	];
+	for (x, y, weight) in corners {
+		field.heights[(y * side + x) as usize] += amount * weight;
+	}
+}
+
+/// Removes `amount` from the pixels of the brush around `position` (without
+/// going below 0), and gives what was removed.
+fn erode_brush(
+	field: &mut Heightfield,
+	brush: &[(i64, i64, f32)],
+	position: [f32; 2],
+	amount: f32,
+) -> f32 {
+	let side = field.side as i64;
+	let (px, py) = (position[0] as i64, position[1] as i64);
+	let mut removed = 0.0;
+	for &(dx, dy, weight) in brush {
+		let (x, y) = (px + dx, py + dy);
+		if (0..side).contains(&x) && (0..side).contains(&y) {
+			let height = &mut field.heights[(y * side + x) as usize];
+			let erosion = f32::min(amount * weight, f32::max(*height, 0.0));
+			*height -= erosion;
+			removed += erosion;
+		}
+	}
+	removed
+}
+
+/// Settings of thermal erosion, with slopes in height per width of the
+/// whole heightfield, so that they do not depend on its resolution.
+#[derive(Clone, Debug)]
+pub struct ThermalErosion {
+	pub iterations: usize,
+	/// The steepest slope that holds, the tangent of the talus angle.
+	pub talus: f32,
+	/// Part of the excess of the steepest slope that moves down at each
+	/// iteration, up to 1 (beyond which slopes overshoot and oscillate).
+	pub rate: f32,
+}
+
+impl ThermalErosion {
+	pub fn new(iterations: usize) -> ThermalErosion {
+		ThermalErosion { iterations, talus: 4.0, rate: 0.5 }
+	}
+
+	pub fn apply(&self, field: &mut Heightfield) {
+		let talus = self.talus / field.side as f32;
+		let mut changes = vec![0.0; field.heights.len()];
+		let mut excesses = Vec::with_capacity(8);
+		for _ in 0..self.iterations {
+			// All the pixels move material at once, from the same heights,
+			// so that the order in which they are visited does not matter.
+			changes.iter_mut().for_each(|change| *change = 0.0);
+			for (index, &height) in field.heights.iter().enumerate() {
+				excesses.clear();
+				excesses.extend(field.neighbors(index).filter_map(|(neighbor, distance)| {
+					let excess = height - field.heights[neighbor] - talus * distance;
+					(excess > 0.0).then_some((neighbor, excess))
+				}));
+				let (excess_sum, max_excess) = excesses
+					.iter()
+					.fold((0.0, 0.0f32), |(sum, max), &(_, excess)| {
+						(sum + excess, max.max(excess))
+					});
+				if excess_sum == 0.0 {
+					continue;
+				}
+				// Half the steepest excess levels the steepest slope, it is
+				// split between the lower neighbors in proportion to theirs.
+				let moved = self.rate * max_excess / 2.0;
+				changes[index] -= moved;
+				for &(neighbor, excess) in &excesses {
+					changes[neighbor] += moved * excess / excess_sum;
+				}
+			}
+			for (height, change) in field.heights.iter_mut().zip(&changes) {
+				*height += change;
+			}
+		}
+	}
+}
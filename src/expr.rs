@@ -0,0 +1,855 @@
+//! A small expression language for patterns, parsed at runtime so that
+//! one-liners can be rendered without recompiling (see `noizebra expr`).
+//!
+//! For example `warp(fbm(p * 10, oct = 5, seed = 1), 0.2) > 0.5`.
+//!
+//! - `p` is the point being colored (in [0, 1]²), `x` and `y` its coordinates
+//!   (also `p.x` and `p.y`), `pi` and `tau` are what they are.
+//! - Numbers and points support `+`, `-`, `*` and `/` (a point times a number
+//!   scales it), comparisons give 0 or 1.
+//! - Functions take positional arguments followed by named ones,
+//!   see `BUILTINS` for the list.
+//! - The whole expression is either a number (rendered in gray from 0 to 1) or
+//!   an `rgb(red, green, blue)` color.
+
+use crate::cellular::{cellular_noise, DistanceMetric};
+use crate::simplex::simplex_noise;
+use crate::{
+	indentity, interpolate, octaves_noise, quintic, smoothcos, smoothstep, LatticeHash, Noise,
+};
+use std::f32::consts::{PI, TAU};
+use std::fmt;
+use std::ops::Range;
+
+/// What went wrong and where, as byte offsets in the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExprError {
+	pub message: String,
+	pub span: Range<usize>,
+}
+
+impl ExprError {
+	fn new(message: impl Into<String>, span: Range<usize>) -> ExprError {
+		ExprError { message: message.into(), span }
+	}
+
+	/// The message followed by the source with the span underlined.
+	pub fn report(&self, source: &str) -> String {
+		let start = source[..self.span.start].chars().count();
+		let length = source[self.span.clone()].chars().count().max(1);
+		format!(
+			"error: {}\n  {source}\n  {}{}",
+			self.message,
+			" ".repeat(start),
+			"^".repeat(length)
+		)
+	}
+}
+
+impl fmt::Display for ExprError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} at {}..{}",
+			self.message, self.span.start, self.span.end
+		)
+	}
+}
+
+impl std::error::Error for ExprError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+	Number(f32),
+	Identifier(String),
+	Symbol(&'static str),
+}
+
+/// Longest first, so that `<=` is not read as `<` then `=`.
+const SYMBOLS: [&str; 14] = [
+	"<=", ">=", "(", ")", ",", "=", "+", "-", "*", "/", "<", ">", ".", "%",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, Range<usize>)>, ExprError> {
+	let mut tokens = Vec::new();
+	let bytes = source.as_bytes();
+	let mut i = 0;
+	while i < source.len() {
+		let c = source[i..].chars().next().unwrap();
+		let start = i;
+		if c.is_whitespace() {
+			i += c.len_utf8();
+		} else if c.is_ascii_digit() || (c == '.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+		{
+			while i < source.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+				i += 1;
+			}
+			// Exponents, like `1e-3`.
+			if i < source.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+				let mut j = i + 1;
+				if j < source.len() && (bytes[j] == b'+' || bytes[j] == b'-') {
+					j += 1;
+				}
+				if j < source.len() && bytes[j].is_ascii_digit() {
+					i = j;
+					while i < source.len() && bytes[i].is_ascii_digit() {
+						i += 1;
+					}
+				}
+			}
+			let number = source[start..i]
+				.parse()
+				.map_err(|_| ExprError::new("invalid number", start..i))?;
+			tokens.push((Token::Number(number), start..i));
+		} else if c.is_alphabetic() || c == '_' {
+			while i < source.len() {
+				let c = source[i..].chars().next().unwrap();
+				if !(c.is_alphanumeric() || c == '_') {
+					break;
+				}
+				i += c.len_utf8();
+			}
+			tokens.push((Token::Identifier(source[start..i].to_string()), start..i));
+		} else if let Some(symbol) = SYMBOLS
+			.iter()
+			.find(|symbol| source[i..].starts_with(**symbol))
+		{
+			i += symbol.len();
+			tokens.push((Token::Symbol(symbol), start..i));
+		} else {
+			let end = i + c.len_utf8();
+			return Err(ExprError::new(
+				format!("unexpected character {c:?}"),
+				start..end,
+			));
+		}
+	}
+	Ok(tokens)
+}
+
+/// An expression as written, before the names are resolved.
+#[derive(Clone, Debug)]
+enum Syntax {
+	Number(f32),
+	Variable(String),
+	Negate(Box<Spanned>),
+	Binary(&'static str, Box<Spanned>, Box<Spanned>),
+	Member(Box<Spanned>, String, Range<usize>),
+	Call {
+		name: String,
+		name_span: Range<usize>,
+		positional: Vec<Spanned>,
+		named: Vec<(String, Range<usize>, Spanned)>,
+	},
+}
+
+#[derive(Clone, Debug)]
+struct Spanned {
+	syntax: Syntax,
+	span: Range<usize>,
+}
+
+struct Parser<'a> {
+	tokens: &'a [(Token, Range<usize>)],
+	position: usize,
+	source_len: usize,
+}
+
+impl Parser<'_> {
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.position).map(|(token, _)| token)
+	}
+
+	fn peek_symbol(&self, symbol: &str) -> bool {
+		matches!(self.peek(), Some(Token::Symbol(next)) if *next == symbol)
+	}
+
+	/// The span of the next token, or an empty span at the end.
+	fn span(&self) -> Range<usize> {
+		match self.tokens.get(self.position) {
+			Some((_, span)) => span.clone(),
+			None => self.source_len..self.source_len,
+		}
+	}
+
+	fn unexpected(&self, expected: &str) -> ExprError {
+		let found = match self.peek() {
+			Some(Token::Number(_)) => "a number".to_string(),
+			Some(Token::Identifier(name)) => format!("`{name}`"),
+			Some(Token::Symbol(symbol)) => format!("`{symbol}`"),
+			None => "the end".to_string(),
+		};
+		ExprError::new(format!("expected {expected}, found {found}"), self.span())
+	}
+
+	fn expect_symbol(&mut self, symbol: &str) -> Result<Range<usize>, ExprError> {
+		if self.peek_symbol(symbol) {
+			self.position += 1;
+			Ok(self.tokens[self.position - 1].1.clone())
+		} else {
+			Err(self.unexpected(&format!("`{symbol}`")))
+		}
+	}
+
+	fn expression(&mut self) -> Result<Spanned, ExprError> {
+		let a = self.additive()?;
+		for symbol in ["<", ">", "<=", ">="] {
+			if self.peek_symbol(symbol) {
+				self.position += 1;
+				let b = self.additive()?;
+				let span = a.span.start..b.span.end;
+				let syntax = Syntax::Binary(symbol, Box::new(a), Box::new(b));
+				return Ok(Spanned { syntax, span });
+			}
+		}
+		Ok(a)
+	}
+
+	fn binary_chain(
+		&mut self,
+		symbols: &[&'static str],
+		operand: fn(&mut Self) -> Result<Spanned, ExprError>,
+	) -> Result<Spanned, ExprError> {
+		let mut a = operand(self)?;
+		while let Some(&symbol) = symbols.iter().find(|symbol| self.peek_symbol(symbol)) {
+			self.position += 1;
+			let b = operand(self)?;
+			let span = a.span.start..b.span.end;
+			a = Spanned {
+				syntax: Syntax::Binary(symbol, Box::new(a), Box::new(b)),
+				span,
+			};
+		}
+		Ok(a)
+	}
+
+	fn additive(&mut self) -> Result<Spanned, ExprError> {
+		self.binary_chain(&["+", "-"], Self::multiplicative)
+	}
+
+	fn multiplicative(&mut self) -> Result<Spanned, ExprError> {
+		self.binary_chain(&["*", "/", "%"], Self::unary)
+	}
+
+	fn unary(&mut self) -> Result<Spanned, ExprError> {
+		if self.peek_symbol("-") {
+			let start = self.span().start;
+			self.position += 1;
+			let operand = self.unary()?;
+			let span = start..operand.span.end;
+			return Ok(Spanned { syntax: Syntax::Negate(Box::new(operand)), span });
+		}
+		let mut operand = self.primary()?;
+		while self.peek_symbol(".") {
+			self.position += 1;
+			let span = self.span();
+			let Some(Token::Identifier(name)) = self.peek().cloned() else {
+				return Err(self.unexpected("`x` or `y`"));
+			};
+			self.position += 1;
+			let whole = operand.span.start..span.end;
+			let syntax = Syntax::Member(Box::new(operand), name, span);
+			operand = Spanned { syntax, span: whole };
+		}
+		Ok(operand)
+	}
+
+	fn primary(&mut self) -> Result<Spanned, ExprError> {
+		let span = self.span();
+		match self.peek().cloned() {
+			Some(Token::Number(number)) => {
+				self.position += 1;
+				Ok(Spanned { syntax: Syntax::Number(number), span })
+			},
+			Some(Token::Identifier(name)) => {
+				self.position += 1;
+				if self.peek_symbol("(") {
+					self.call(name, span)
+				} else {
+					Ok(Spanned { syntax: Syntax::Variable(name), span })
+				}
+			},
+			Some(Token::Symbol("(")) => {
+				self.position += 1;
+				let inner = self.expression()?;
+				let end = self.expect_symbol(")")?.end;
+				Ok(Spanned { syntax: inner.syntax, span: span.start..end })
+			},
+			_ => Err(self.unexpected("an expression")),
+		}
+	}
+
+	fn call(&mut self, name: String, name_span: Range<usize>) -> Result<Spanned, ExprError> {
+		self.expect_symbol("(")?;
+		let mut positional = Vec::new();
+		let mut named = Vec::new();
+		while !self.peek_symbol(")") {
+			let is_named = matches!(self.peek(), Some(Token::Identifier(_)))
+				&& self
+					.tokens
+					.get(self.position + 1)
+					.is_some_and(|(token, _)| *token == Token::Symbol("="));
+			if is_named {
+				let span = self.span();
+				let Some(Token::Identifier(argument)) = self.peek().cloned() else {
+					unreachable!()
+				};
+				self.position += 2;
+				named.push((argument, span, self.expression()?));
+			} else {
+				let argument = self.expression()?;
+				if !named.is_empty() {
+					return Err(ExprError::new(
+						"positional arguments go before named ones",
+						argument.span,
+					));
+				}
+				positional.push(argument);
+			}
+			if !self.peek_symbol(")") {
+				if !self.peek_symbol(",") {
+					return Err(self.unexpected("`,` or `)`"));
+				}
+				self.position += 1;
+			}
+		}
+		let end = self.expect_symbol(")")?.end;
+		let syntax = Syntax::Call {
+			name,
+			name_span: name_span.clone(),
+			positional,
+			named,
+		};
+		Ok(Spanned { syntax, span: name_span.start..end })
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Type {
+	Number,
+	Point,
+	Color,
+}
+
+impl Type {
+	fn name(self) -> &'static str {
+		match self {
+			Type::Number => "a number",
+			Type::Point => "a point",
+			Type::Color => "a color",
+		}
+	}
+}
+
+/// An expression with its names resolved and its types checked,
+/// so that evaluating it cannot fail.
+#[derive(Clone, Debug)]
+enum Node {
+	Number(f32),
+	Point,
+	Vec2(Box<[Node; 2]>),
+	Axis(Box<Node>, usize),
+	Negate(Box<Node>),
+	/// Either operand can be a point, the other one being broadcast.
+	Arithmetic(fn(f32, f32) -> f32, Box<[Node; 2]>),
+	Compare(fn(&f32, &f32) -> bool, Box<[Node; 2]>),
+	Math(fn(f32) -> f32, Box<Node>),
+	Math2(fn(f32, f32) -> f32, Box<[Node; 2]>),
+	Length(Box<Node>),
+	Clamp(Box<[Node; 3]>),
+	Mix(Box<[Node; 3]>),
+	If(Box<[Node; 3]>),
+	Interpolate(Smoothing, Box<[Node; 5]>),
+	Noise {
+		kind: NoiseKind,
+		point: Box<Node>,
+		channels: Vec<i32>,
+		seed: Option<u64>,
+	},
+	Warp {
+		source: Box<Node>,
+		strength: Box<Node>,
+		fields: Box<[Node; 2]>,
+	},
+	Rgb(Box<[Node; 3]>),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum NoiseKind {
+	Octaves(i32),
+	Simplex,
+	Cellular(DistanceMetric),
+}
+
+/// The functions, with their positional parameters and their named ones
+/// (with a default value).
+pub const BUILTINS: [(&str, &str); 24] = [
+	("fbm", "fbm(point, oct = 6, ch, seed): octaves noise"),
+	("simplex", "simplex(point, ch, seed): simplex noise"),
+	(
+		"cellular",
+		"cellular(point, ch, seed, metric = euclidean): distance to the nearest feature point",
+	),
+	(
+		"warp",
+		"warp(source, strength, dx = fbm(p * 10, oct = 5, ch = 1), dy = fbm(p * 10, oct = 5, ch = 2)): \
+		 `source` evaluated with `p` moved by `(d * 2 - 1) * strength`",
+	),
+	(
+		"interpolate",
+		"interpolate(value, from_inf, from_sup, to_inf, to_sup, smooth = linear): \
+		 `smooth` is one of linear, smoothstep, smoothcos and quintic",
+	),
+	("smoothstep", "smoothstep(number)"),
+	("smoothcos", "smoothcos(number)"),
+	("quintic", "quintic(number)"),
+	("atan2", "atan2(y, x)"),
+	("hypot", "hypot(x, y) or hypot(point)"),
+	("vec", "vec(x, y): a point"),
+	("rgb", "rgb(red, green, blue): a color, only for the whole expression"),
+	("mix", "mix(a, b, t)"),
+	("clamp", "clamp(value, min, max)"),
+	("if", "if(condition, a, b): `a` where the condition is not 0, `b` elsewhere"),
+	("min", "min(a, b)"),
+	("max", "max(a, b)"),
+	("pow", "pow(base, exponent)"),
+	("abs", "abs(number)"),
+	("sqrt", "sqrt(number)"),
+	("floor", "floor(number)"),
+	("fract", "fract(number)"),
+	("sin", "sin(number)"),
+	("cos", "cos(number)"),
+];
+
+struct Checker;
+
+impl Checker {
+	fn check(&self, expression: &Spanned) -> Result<(Node, Type), ExprError> {
+		let span = expression.span.clone();
+		match &expression.syntax {
+			Syntax::Number(number) => Ok((Node::Number(*number), Type::Number)),
+			Syntax::Variable(name) => match name.as_str() {
+				"p" => Ok((Node::Point, Type::Point)),
+				"x" => Ok((Node::Axis(Box::new(Node::Point), 0), Type::Number)),
+				"y" => Ok((Node::Axis(Box::new(Node::Point), 1), Type::Number)),
+				"pi" => Ok((Node::Number(PI), Type::Number)),
+				"tau" => Ok((Node::Number(TAU), Type::Number)),
+				_ => Err(ExprError::new(format!("unknown variable `{name}`"), span)),
+			},
+			Syntax::Negate(operand) => {
+				let (node, ty) = self.check(operand)?;
+				if ty == Type::Color {
+					return Err(ExprError::new("cannot negate a color", span));
+				}
+				Ok((Node::Negate(Box::new(node)), ty))
+			},
+			Syntax::Member(operand, name, name_span) => {
+				let node = self.expect(operand, Type::Point)?;
+				let axis = match name.as_str() {
+					"x" => 0,
+					"y" => 1,
+					_ => {
+						let message = format!("points only have `x` and `y`, not `{name}`");
+						return Err(ExprError::new(message, name_span.clone()));
+					},
+				};
+				Ok((Node::Axis(Box::new(node), axis), Type::Number))
+			},
+			Syntax::Binary(symbol, a, b) => self.binary(symbol, a, b),
+			Syntax::Call { name, name_span, positional, named } => {
+				self.call(name, name_span, &span, positional, named)
+			},
+		}
+	}
+
+	fn expect(&self, expression: &Spanned, expected: Type) -> Result<Node, ExprError> {
+		let (node, ty) = self.check(expression)?;
+		if ty != expected {
+			let message = format!("expected {}, found {}", expected.name(), ty.name());
+			return Err(ExprError::new(message, expression.span.clone()));
+		}
+		Ok(node)
+	}
+
+	fn binary(&self, symbol: &str, a: &Spanned, b: &Spanned) -> Result<(Node, Type), ExprError> {
+		let (a_node, a_type) = self.check(a)?;
+		let (b_node, b_type) = self.check(b)?;
+		for (ty, operand) in [(a_type, a), (b_type, b)] {
+			if ty == Type::Color {
+				let message = format!("`{symbol}` does not work on colors");
+				return Err(ExprError::new(message, operand.span.clone()));
+			}
+		}
+		let nodes = Box::new([a_node, b_node]);
+		let compare: Option<fn(&f32, &f32) -> bool> = match symbol {
+			"<" => Some(f32::lt),
+			">" => Some(f32::gt),
+			"<=" => Some(f32::le),
+			">=" => Some(f32::ge),
+			_ => None,
+		};
+		if let Some(compare) = compare {
+			for (ty, operand) in [(a_type, a), (b_type, b)] {
+				if ty != Type::Number {
+					let message = format!("`{symbol}` compares numbers, not {}", ty.name());
+					return Err(ExprError::new(message, operand.span.clone()));
+				}
+			}
+			return Ok((Node::Compare(compare, nodes), Type::Number));
+		}
+		let op: fn(f32, f32) -> f32 = match symbol {
+			"+" => |a, b| a + b,
+			"-" => |a, b| a - b,
+			"*" => |a, b| a * b,
+			"/" => |a, b| a / b,
+			"%" => f32::rem_euclid,
+			_ => unreachable!(),
+		};
+		let ty = if a_type == Type::Point || b_type == Type::Point {
+			Type::Point
+		} else {
+			Type::Number
+		};
+		Ok((Node::Arithmetic(op, nodes), ty))
+	}
+
+	fn call(
+		&self,
+		name: &str,
+		name_span: &Range<usize>,
+		span: &Range<usize>,
+		positional: &[Spanned],
+		named: &[(String, Range<usize>, Spanned)],
+	) -> Result<(Node, Type), ExprError> {
+		let Some((_, signature)) = BUILTINS.iter().find(|(builtin, _)| *builtin == name) else {
+			return Err(ExprError::new(
+				format!("unknown function `{name}`"),
+				name_span.clone(),
+			));
+		};
+		let allowed_named: &[&str] = match name {
+			"fbm" => &["oct", "ch", "seed"],
+			"simplex" => &["ch", "seed"],
+			"cellular" => &["ch", "seed", "metric"],
+			"warp" => &["dx", "dy"],
+			"interpolate" => &["smooth"],
+			_ => &[],
+		};
+		for (argument, argument_span, _) in named {
+			if !allowed_named.contains(&argument.as_str()) {
+				let message = format!("unknown argument `{argument}`, the signature is {signature}");
+				return Err(ExprError::new(message, argument_span.clone()));
+			}
+		}
+		let named_argument = |argument: &str| {
+			named
+				.iter()
+				.find(|(name, _, _)| name == argument)
+				.map(|(_, _, value)| value)
+		};
+		let count = match name {
+			"fbm" | "simplex" | "cellular" | "smoothstep" | "smoothcos" | "quintic" | "abs"
+			| "sqrt" | "floor" | "fract" | "sin" | "cos" => 1,
+			"warp" | "atan2" | "vec" | "min" | "max" | "pow" => 2,
+			"rgb" | "mix" | "clamp" | "if" => 3,
+			"interpolate" => 5,
+			// `hypot` takes a point or two numbers.
+			_ => positional.len().clamp(1, 2),
+		};
+		if positional.len() != count {
+			let message = format!(
+				"`{name}` expects {count} positional argument{}, found {}; the signature is {signature}",
+				if count == 1 { "" } else { "s" },
+				positional.len()
+			);
+			return Err(ExprError::new(message, span.clone()));
+		}
+		let numbers = |range: Range<usize>| -> Result<Vec<Node>, ExprError> {
+			positional[range]
+				.iter()
+				.map(|argument| self.expect(argument, Type::Number))
+				.collect()
+		};
+		let math = |op: fn(f32) -> f32| -> Result<(Node, Type), ExprError> {
+			let [x] = numbers(0..1)?.try_into().unwrap();
+			Ok((Node::Math(op, Box::new(x)), Type::Number))
+		};
+		let math2 = |op: fn(f32, f32) -> f32| -> Result<(Node, Type), ExprError> {
+			let nodes: [Node; 2] = numbers(0..2)?.try_into().unwrap();
+			Ok((Node::Math2(op, Box::new(nodes)), Type::Number))
+		};
+		let three = || -> Result<Box<[Node; 3]>, ExprError> {
+			Ok(Box::new(numbers(0..3)?.try_into().unwrap()))
+		};
+		match name {
+			"fbm" | "simplex" | "cellular" => {
+				let point = self.expect(&positional[0], Type::Point)?;
+				let channels = match named_argument("ch") {
+					Some(channel) => vec![self.integer(channel)? as i32],
+					None => Vec::new(),
+				};
+				let seed = match named_argument("seed") {
+					Some(seed) => Some(self.integer(seed)? as u64),
+					None => None,
+				};
+				let kind = match name {
+					"fbm" => NoiseKind::Octaves(match named_argument("oct") {
+						Some(octave_count) => self.integer(octave_count)? as i32,
+						None => 6,
+					}),
+					"simplex" => NoiseKind::Simplex,
+					_ => NoiseKind::Cellular(match named_argument("metric") {
+						Some(metric) => self.choice(metric, &METRICS)?,
+						None => DistanceMetric::Euclidean,
+					}),
+				};
+				let point = Box::new(point);
+				Ok((Node::Noise { kind, point, channels, seed }, Type::Number))
+			},
+			"warp" => {
+				let (source, ty) = self.check(&positional[0])?;
+				let strength = self.expect(&positional[1], Type::Number)?;
+				let field = |argument: &str, channel: i32| match named_argument(argument) {
+					Some(field) => self.expect(field, Type::Number),
+					None => Ok(default_warp_field(channel)),
+				};
+				let fields = Box::new([field("dx", 1)?, field("dy", 2)?]);
+				let (source, strength) = (Box::new(source), Box::new(strength));
+				Ok((Node::Warp { source, strength, fields }, ty))
+			},
+			"interpolate" => {
+				let smoothing = match named_argument("smooth") {
+					Some(smoothing) => self.choice(smoothing, &SMOOTHINGS)?,
+					None => indentity,
+				};
+				let nodes: [Node; 5] = numbers(0..5)?.try_into().unwrap();
+				Ok((Node::Interpolate(smoothing, Box::new(nodes)), Type::Number))
+			},
+			"hypot" if positional.len() == 1 => {
+				let point = self.expect(&positional[0], Type::Point)?;
+				Ok((Node::Length(Box::new(point)), Type::Number))
+			},
+			"hypot" => math2(f32::hypot),
+			"vec" => {
+				let nodes: [Node; 2] = numbers(0..2)?.try_into().unwrap();
+				Ok((Node::Vec2(Box::new(nodes)), Type::Point))
+			},
+			"rgb" => Ok((Node::Rgb(three()?), Type::Color)),
+			"mix" => Ok((Node::Mix(three()?), Type::Number)),
+			"clamp" => Ok((Node::Clamp(three()?), Type::Number)),
+			"if" => Ok((Node::If(three()?), Type::Number)),
+			"smoothstep" => math(smoothstep),
+			"smoothcos" => math(smoothcos),
+			"quintic" => math(quintic),
+			"abs" => math(f32::abs),
+			"sqrt" => math(f32::sqrt),
+			"floor" => math(f32::floor),
+			"fract" => math(f32::fract),
+			"sin" => math(f32::sin),
+			"cos" => math(f32::cos),
+			"atan2" => math2(f32::atan2),
+			"min" => math2(f32::min),
+			"max" => math2(f32::max),
+			"pow" => math2(f32::powf),
+			_ => unreachable!("{name} is in BUILTINS but not checked"),
+		}
+	}
+
+	/// Noise settings are integer literals.
+	fn integer(&self, expression: &Spanned) -> Result<i64, ExprError> {
+		let value = match &expression.syntax {
+			Syntax::Number(number) => Some(*number),
+			Syntax::Negate(operand) => match operand.syntax {
+				Syntax::Number(number) => Some(-number),
+				_ => None,
+			},
+			_ => None,
+		};
+		match value {
+			Some(value) if value.fract() == 0.0 => Ok(value as i64),
+			_ => Err(ExprError::new(
+				"expected an integer",
+				expression.span.clone(),
+			)),
+		}
+	}
+
+	/// Options given by name, like `smooth = smoothcos`.
+	fn choice<T: Copy>(&self, expression: &Spanned, choices: &[(&str, T)]) -> Result<T, ExprError> {
+		if let Syntax::Variable(name) = &expression.syntax {
+			if let Some((_, value)) = choices.iter().find(|(choice, _)| choice == name) {
+				return Ok(*value);
+			}
+		}
+		let names: Vec<_> = choices.iter().map(|(name, _)| *name).collect();
+		let message = format!("expected one of {}", names.join(", "));
+		Err(ExprError::new(message, expression.span.clone()))
+	}
+}
+
+/// The smoothing functions that `interpolate` takes.
+type Smoothing = fn(f32) -> f32;
+
+const SMOOTHINGS: [(&str, Smoothing); 4] = [
+	("linear", indentity),
+	("smoothstep", smoothstep),
+	("smoothcos", smoothcos),
+	("quintic", quintic),
+];
+
+const METRICS: [(&str, DistanceMetric); 3] = [
+	("euclidean", DistanceMetric::Euclidean),
+	("manhattan", DistanceMetric::Manhattan),
+	("chebyshev", DistanceMetric::Chebyshev),
+];
+
+/// `fbm(p * 10, oct = 5, ch = channel)`, like the fields of generator 05.
+fn default_warp_field(channel: i32) -> Node {
+	let point = Node::Arithmetic(|a, b| a * b, Box::new([Node::Point, Node::Number(10.0)]));
+	Node::Noise {
+		kind: NoiseKind::Octaves(5),
+		point: Box::new(point),
+		channels: vec![channel],
+		seed: None,
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Value {
+	Number(f32),
+	Point([f32; 2]),
+	Color([f32; 3]),
+}
+
+impl Value {
+	fn number(self) -> f32 {
+		match self {
+			Value::Number(number) => number,
+			_ => unreachable!("the types are checked"),
+		}
+	}
+
+	fn point(self) -> [f32; 2] {
+		match self {
+			Value::Point(point) => point,
+			_ => unreachable!("the types are checked"),
+		}
+	}
+}
+
+impl Node {
+	fn evaluate<H: LatticeHash>(&self, noise: &Noise<H>, p: &[f32; 2]) -> Value {
+		let number = |node: &Node| node.evaluate(noise, p).number();
+		match self {
+			Node::Number(number) => Value::Number(*number),
+			Node::Point => Value::Point(*p),
+			Node::Vec2(nodes) => Value::Point([number(&nodes[0]), number(&nodes[1])]),
+			Node::Axis(point, axis) => Value::Number(point.evaluate(noise, p).point()[*axis]),
+			Node::Negate(operand) => match operand.evaluate(noise, p) {
+				Value::Number(number) => Value::Number(-number),
+				Value::Point(point) => Value::Point(point.map(|x| -x)),
+				Value::Color(_) => unreachable!("the types are checked"),
+			},
+			Node::Arithmetic(op, nodes) => {
+				match (nodes[0].evaluate(noise, p), nodes[1].evaluate(noise, p)) {
+					(Value::Number(a), Value::Number(b)) => Value::Number(op(a, b)),
+					(Value::Point(a), Value::Number(b)) => Value::Point(a.map(|a| op(a, b))),
+					(Value::Number(a), Value::Point(b)) => Value::Point(b.map(|b| op(a, b))),
+					(Value::Point(a), Value::Point(b)) => Value::Point([op(a[0], b[0]), op(a[1], b[1])]),
+					_ => unreachable!("the types are checked"),
+				}
+			},
+			Node::Compare(compare, nodes) => {
+				let is_true = compare(&number(&nodes[0]), &number(&nodes[1]));
+				Value::Number(if is_true { 1.0 } else { 0.0 })
+			},
+			Node::Math(op, x) => Value::Number(op(number(x))),
+			Node::Math2(op, nodes) => Value::Number(op(number(&nodes[0]), number(&nodes[1]))),
+			Node::Length(point) => {
+				let [x, y] = point.evaluate(noise, p).point();
+				Value::Number(f32::hypot(x, y))
+			},
+			Node::Clamp(nodes) => {
+				let [value, min, max] = [0, 1, 2].map(|i| number(&nodes[i]));
+				Value::Number(value.max(min).min(max))
+			},
+			Node::Mix(nodes) => {
+				let [a, b, t] = [0, 1, 2].map(|i| number(&nodes[i]));
+				Value::Number(interpolate(&indentity, t, 0.0, 1.0, a, b))
+			},
+			// Only the chosen side is evaluated.
+			Node::If(nodes) => {
+				if number(&nodes[0]) != 0.0 {
+					Value::Number(number(&nodes[1]))
+				} else {
+					Value::Number(number(&nodes[2]))
+				}
+			},
+			Node::Interpolate(smoothing, nodes) => {
+				let [x, x_inf, x_sup, dst_inf, dst_sup] = [0, 1, 2, 3, 4].map(|i| number(&nodes[i]));
+				Value::Number(interpolate(smoothing, x, x_inf, x_sup, dst_inf, dst_sup))
+			},
+			Node::Noise { kind, point, channels, seed } => {
+				let point = point.evaluate(noise, p).point();
+				let noise = &Noise { seed: seed.unwrap_or(noise.seed), ..*noise };
+				Value::Number(match *kind {
+					NoiseKind::Octaves(octave_count) => {
+						octaves_noise(noise, octave_count, &point, channels)
+					},
+					NoiseKind::Simplex => simplex_noise(noise, &point, channels),
+					NoiseKind::Cellular(metric) => cellular_noise(noise, &point, channels, metric).f1,
+				})
+			},
+			// Same as `NoiseFn::warp`.
+			Node::Warp { source, strength, fields } => {
+				let strength = number(strength);
+				let mut warped = *p;
+				for (axis, x) in warped.iter_mut().enumerate() {
+					*x += (number(&fields[axis]) * 2.0 - 1.0) * strength;
+				}
+				source.evaluate(noise, &warped)
+			},
+			Node::Rgb(nodes) => Value::Color([0, 1, 2].map(|i| number(&nodes[i]))),
+		}
+	}
+}
+
+/// A parsed and checked expression, ready to be rendered.
+#[derive(Clone, Debug)]
+pub struct Expression {
+	root: Node,
+}
+
+impl Expression {
+	pub fn parse(source: &str) -> Result<Expression, ExprError> {
+		let tokens = tokenize(source)?;
+		let mut parser = Parser {
+			tokens: &tokens,
+			position: 0,
+			source_len: source.len(),
+		};
+		let syntax = parser.expression()?;
+		if parser.peek().is_some() {
+			return Err(parser.unexpected("the end"));
+		}
+		let (root, ty) = Checker.check(&syntax)?;
+		if ty == Type::Point {
+			let message = "the expression is a point, it should be a number or a color";
+			return Err(ExprError::new(message, syntax.span));
+		}
+		Ok(Expression { root })
+	}
+
+	/// The color of the point `p`, numbers being shades of gray.
+	pub fn color<H: LatticeHash>(&self, noise: &Noise<H>, p: &[f32; 2]) -> image::Rgb<u8> {
+		let to_u8 = |value: f32| (value * 255.0) as u8;
+		match self.root.evaluate(noise, p) {
+			Value::Number(value) => {
+				let gray = to_u8(value);
+				image::Rgb([gray, gray, gray])
+			},
+			Value::Color(color) => image::Rgb(color.map(to_u8)),
+			Value::Point(_) => unreachable!("the types are checked"),
+		}
+	}
+}
@@ -0,0 +1,413 @@
+//! Fractal sums of noise octaves, with more knobs than `octaves_noise`.
+
+use crate::{
+	quintic, raw_noise_multi, raw_noise_node, raw_noise_with_gradient, smoothcos, Coord,
+	FloatMapping, LatticeHash, Noise, NoiseVersion, SplitMixHash,
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::num::NonZeroU32;
+
+/// How the octaves are combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FractalMode {
+	/// Plain weighted average of the octaves.
+	#[default]
+	Fbm,
+	/// Octaves folded around their mean by squaring,
+	/// which makes soft valleys and puffy tops.
+	Billow,
+	/// Sum of the absolute values of the octaves (centered on their mean),
+	/// which makes sharp creases in the valleys.
+	Turbulence,
+	/// Musgrave's ridged multifractal, sharp ridges where the octaves cross
+	/// their mean, with details that get rougher near the ridges.
+	/// The usual values are 1 for the `offset` and 2 for the `sharpness`.
+	Ridged { offset: f32, sharpness: f32 },
+	/// Musgrave's hybrid multifractal, smooth valleys and rough peaks.
+	/// The usual `offset` is around 0.7.
+	Hybrid { offset: f32 },
+	/// Musgrave's heterogeneous terrain, where the amplitude of each octave
+	/// is scaled by the value so far. The usual `offset` is around 0.7.
+	HeteroTerrain { offset: f32 },
+}
+
+/// Settings of a fractal Brownian motion, a sum of octaves of noise where each
+/// octave has a higher frequency and a lower amplitude than the previous one.
+///
+/// In patterns, the settings left out are the ones of `Fbm::new`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Fbm {
+	/// Number of octaves, a fractional part fades the last octave in
+	/// so that the result is continuous in the octave count.
+	pub octaves: f32,
+	/// Frequency multiplier from one octave to the next.
+	#[serde(default = "default_lacunarity")]
+	pub lacunarity: f32,
+	/// Amplitude multiplier from one octave to the next.
+	#[serde(default = "default_gain")]
+	pub gain: f32,
+	/// Weight of each octave on top of the gain, missing weights are 1.
+	#[serde(default)]
+	pub weights: Vec<f32>,
+	#[serde(default)]
+	pub mode: FractalMode,
+	/// Gives each octave its own rotation and offset (derived from the seed),
+	/// so that the lattices of the octaves are not aligned on the origin.
+	#[serde(default = "default_decorrelate")]
+	pub decorrelate: bool,
+}
+
+fn default_lacunarity() -> f32 {
+	2.0
+}
+
+fn default_gain() -> f32 {
+	0.5
+}
+
+fn default_decorrelate() -> bool {
+	true
+}
+
+impl Fbm {
+	/// Same settings as `octaves_noise`, except for the decorrelation.
+	pub fn new(octaves: f32) -> Fbm {
+		Fbm {
+			octaves,
+			lacunarity: default_lacunarity(),
+			gain: default_gain(),
+			weights: vec![],
+			mode: FractalMode::Fbm,
+			decorrelate: default_decorrelate(),
+		}
+	}
+
+	pub fn sample<H: LatticeHash, C: Coord, const D: usize>(
+		&self,
+		noise: &Noise<H>,
+		xs: &[C; D],
+		channels: &[i32],
+	) -> f32 {
+		self.sample_multi(noise, xs, &[channels])[0]
+	}
+
+	/// Same as `sample` for K channels at once, each octave is sampled
+	/// with `raw_noise_multi`.
+	pub fn sample_multi<H: LatticeHash, C: Coord, const D: usize, const K: usize>(
+		&self,
+		noise: &Noise<H>,
+		xs: &[C; D],
+		channels: &[&[i32]; K],
+	) -> [f32; K] {
+		let mut scaled_xs = *xs;
+		let mut frequency = 1.0;
+		let mut value_sums = [0.0; K];
+		let mut coef_sum = 0.0;
+		let mut coef = 1.0;
+		// The multifractal modes make the octaves depend on the previous ones.
+		let mut previouses = [1.0; K];
+		let octave_count = f32::ceil(self.octaves) as usize;
+		for i in 0..octave_count {
+			let fade = octave_fade(noise, self.octaves - i as f32);
+			let weight = coef * self.weights.get(i).copied().unwrap_or(1.0) * fade;
+			let (octave_noise, octave_xs, _scales) = self.octave(noise, i, xs, &scaled_xs, frequency);
+			let values = raw_noise_multi(&octave_noise, &octave_xs, channels);
+			for ((value, value_sum), previous) in values
+				.into_iter()
+				.zip(value_sums.iter_mut())
+				.zip(previouses.iter_mut())
+			{
+				let signed = value * 2.0 - 1.0;
+				match self.mode {
+					FractalMode::Fbm => *value_sum += weight * value,
+					FractalMode::Billow => *value_sum += weight * signed * signed,
+					FractalMode::Turbulence => *value_sum += weight * signed.abs(),
+					FractalMode::Ridged { offset, sharpness } => {
+						let ridge = offset - signed.abs();
+						let ridge = ridge * ridge * *previous / (offset * offset);
+						*value_sum += weight * ridge;
+						*previous = f32::clamp(ridge * sharpness, 0.0, 1.0);
+					},
+					FractalMode::Hybrid { offset } => {
+						let signal = (signed + offset) / (1.0 + offset);
+						*value_sum += weight * signal * *previous;
+						*previous = f32::min(*previous * signal, 1.0);
+					},
+					FractalMode::HeteroTerrain { offset } => {
+						let signal = (signed + offset) / (1.0 + offset);
+						let increment = signal * if i == 0 { 1.0 } else { *value_sum / coef_sum };
+						*value_sum += weight * increment;
+					},
+				}
+			}
+			coef_sum += weight;
+			coef *= self.gain;
+			frequency *= self.lacunarity;
+			scaled_xs
+				.iter_mut()
+				.for_each(|x| *x = *x * C::from_f32(self.lacunarity));
+		}
+		value_sums.map(|value_sum| match self.mode {
+			FractalMode::Fbm | FractalMode::Billow | FractalMode::Turbulence => value_sum / coef_sum,
+			// Could go a bit below 0 or above 1 with unusual offsets.
+			FractalMode::Ridged { .. }
+			| FractalMode::Hybrid { .. }
+			| FractalMode::HeteroTerrain { .. } => f32::clamp(value_sum / coef_sum, 0.0, 1.0),
+		})
+	}
+
+	/// Same as `sample` but also gives the partial derivatives of the result
+	/// along each axis.
+	pub fn sample_with_gradient<H: LatticeHash, C: Coord, const D: usize>(
+		&self,
+		noise: &Noise<H>,
+		xs: &[C; D],
+		channels: &[i32],
+	) -> (f32, [f32; D]) {
+		// This follows `sample` step by step, along with the derivatives
+		// of every intermediate value (named with a `d_` prefix).
+		let mut scaled_xs = *xs;
+		let mut frequency = 1.0;
+		let mut value_sum = 0.0;
+		let mut d_value_sum = [0.0; D];
+		let mut coef_sum = 0.0;
+		let mut coef = 1.0;
+		let mut previous = 1.0;
+		let mut d_previous = [0.0; D];
+		let octave_count = f32::ceil(self.octaves) as usize;
+		for i in 0..octave_count {
+			let fade = octave_fade(noise, self.octaves - i as f32);
+			let weight = coef * self.weights.get(i).copied().unwrap_or(1.0) * fade;
+			let (octave_noise, octave_xs, scales) = self.octave(noise, i, xs, &scaled_xs, frequency);
+			let (value, mut d_value) = raw_noise_with_gradient(&octave_noise, &octave_xs, channels);
+			if self.decorrelate {
+				d_value = decorrelate_octave_gradient(&octave_noise, i, &d_value);
+			}
+			d_value
+				.iter_mut()
+				.zip(scales)
+				.for_each(|(d, scale)| *d *= scale);
+			let signed = value * 2.0 - 1.0;
+			let d_signed = d_value.map(|d| d * 2.0);
+			match self.mode {
+				FractalMode::Fbm => {
+					value_sum += weight * value;
+					add_scaled(&mut d_value_sum, &d_value, weight);
+				},
+				FractalMode::Billow => {
+					value_sum += weight * signed * signed;
+					add_scaled(&mut d_value_sum, &d_signed, weight * 2.0 * signed);
+				},
+				FractalMode::Turbulence => {
+					value_sum += weight * signed.abs();
+					add_scaled(&mut d_value_sum, &d_signed, weight * signed.signum());
+				},
+				FractalMode::Ridged { offset, sharpness } => {
+					let ridge_base = offset - signed.abs();
+					let d_ridge_base = d_signed.map(|d| -d * signed.signum());
+					let ridge = ridge_base * ridge_base * previous / (offset * offset);
+					let mut d_ridge = [0.0; D];
+					add_scaled(&mut d_ridge, &d_ridge_base, 2.0 * ridge_base * previous);
+					add_scaled(&mut d_ridge, &d_previous, ridge_base * ridge_base);
+					d_ridge.iter_mut().for_each(|d| *d /= offset * offset);
+					value_sum += weight * ridge;
+					add_scaled(&mut d_value_sum, &d_ridge, weight);
+					let unclamped = ridge * sharpness;
+					previous = f32::clamp(unclamped, 0.0, 1.0);
+					d_previous = if (0.0..=1.0).contains(&unclamped) {
+						d_ridge.map(|d| d * sharpness)
+					} else {
+						[0.0; D]
+					};
+				},
+				FractalMode::Hybrid { offset } => {
+					let signal = (signed + offset) / (1.0 + offset);
+					let d_signal = d_signed.map(|d| d / (1.0 + offset));
+					value_sum += weight * signal * previous;
+					add_scaled(&mut d_value_sum, &d_signal, weight * previous);
+					add_scaled(&mut d_value_sum, &d_previous, weight * signal);
+					let unclamped = previous * signal;
+					if unclamped < 1.0 {
+						let mut d_unclamped = [0.0; D];
+						add_scaled(&mut d_unclamped, &d_previous, signal);
+						add_scaled(&mut d_unclamped, &d_signal, previous);
+						d_previous = d_unclamped;
+						previous = unclamped;
+					} else {
+						d_previous = [0.0; D];
+						previous = 1.0;
+					}
+				},
+				FractalMode::HeteroTerrain { offset } => {
+					let signal = (signed + offset) / (1.0 + offset);
+					let d_signal = d_signed.map(|d| d / (1.0 + offset));
+					if i == 0 {
+						value_sum += weight * signal;
+						add_scaled(&mut d_value_sum, &d_signal, weight);
+					} else {
+						let so_far = value_sum / coef_sum;
+						let d_so_far = d_value_sum.map(|d| d / coef_sum);
+						value_sum += weight * (signal * so_far);
+						add_scaled(&mut d_value_sum, &d_signal, weight * so_far);
+						add_scaled(&mut d_value_sum, &d_so_far, weight * signal);
+					}
+				},
+			}
+			coef_sum += weight;
+			coef *= self.gain;
+			frequency *= self.lacunarity;
+			scaled_xs
+				.iter_mut()
+				.for_each(|x| *x = *x * C::from_f32(self.lacunarity));
+		}
+		let value = value_sum / coef_sum;
+		let d_value = d_value_sum.map(|d| d / coef_sum);
+		match self.mode {
+			FractalMode::Fbm | FractalMode::Billow | FractalMode::Turbulence => (value, d_value),
+			FractalMode::Ridged { .. }
+			| FractalMode::Hybrid { .. }
+			| FractalMode::HeteroTerrain { .. } => {
+				if (0.0..=1.0).contains(&value) {
+					(value, d_value)
+				} else {
+					(f32::clamp(value, 0.0, 1.0), [0.0; D])
+				}
+			},
+		}
+	}
+
+	/// The noise context and the coordinates with which the given octave
+	/// is sampled, along with the scale from `xs` to these coordinates on each
+	/// axis (the rotation of the decorrelation aside).
+	///
+	/// `scaled_xs` is `xs` scaled by `frequency`, it is kept by the caller
+	/// as multiplying by the lacunarity at each octave is exact for powers of 2.
+	fn octave<H: LatticeHash, C: Coord, const D: usize>(
+		&self,
+		noise: &Noise<H>,
+		octave: usize,
+		xs: &[C; D],
+		scaled_xs: &[C; D],
+		frequency: f32,
+	) -> (Noise<H>, [C; D], [f32; D]) {
+		let mut octave_noise = *noise;
+		let mut octave_xs = *scaled_xs;
+		let mut scales = [frequency; D];
+		// The periods are scaled with the octave, and rounded so that the octave
+		// still tiles, which adjusts the frequency a bit on the periodic axes.
+		for axis in 0..D.min(noise.periods.len()) {
+			if let Some(period) = noise.periods[axis] {
+				let period = period.get() as f32;
+				let octave_period = f32::max(f32::round(period * frequency), 1.0);
+				scales[axis] = octave_period / period;
+				octave_xs[axis] = xs[axis] * C::from_f32(scales[axis]);
+				octave_noise.periods[axis] = NonZeroU32::new(octave_period as u32);
+			}
+		}
+		if self.decorrelate {
+			octave_xs = decorrelate_octave(&octave_noise, octave, &octave_xs);
+		}
+		(octave_noise, octave_xs, scales)
+	}
+}
+
+fn add_scaled<const D: usize>(sum: &mut [f32; D], xs: &[f32; D], scale: f32) {
+	sum.iter_mut()
+		.zip(xs)
+		.for_each(|(sum, x)| *sum += x * scale);
+}
+
+/// How much an octave is faded in, from 0 to 1, where `x` is the octave count
+/// minus the index of the octave.
+fn octave_fade<H: LatticeHash>(noise: &Noise<H>, x: f32) -> f32 {
+	match noise.float_mapping {
+		FloatMapping::Cos => smoothcos(x),
+		FloatMapping::Portable => quintic(x),
+	}
+}
+
+/// Sine and cosine of the rotation of the given octave in the plane of `axis`
+/// and the axis before.
+///
+/// With `FloatMapping::Portable`, they are computed from the tangent of half
+/// the angle, which avoids `f32::sin_cos`. That tangent is in [-1, 1] so the
+/// angles are in [-90°, 90°], which still gives every orientation of the lattice.
+fn decorrelation_rotation<H: LatticeHash>(
+	noise: &Noise<H>,
+	octave: usize,
+	axis: usize,
+) -> (f32, f32) {
+	let value = decorrelation_value(noise, octave, axis, 0);
+	match noise.float_mapping {
+		FloatMapping::Cos => f32::sin_cos(value * TAU),
+		FloatMapping::Portable => {
+			let tangent = value * 2.0 - 1.0;
+			let squared = tangent * tangent;
+			let sum = 1.0 + squared;
+			(2.0 * tangent / sum, (1.0 - squared) / sum)
+		},
+	}
+}
+
+/// A pseudo-random value in [0, 1) for the `kind` of transform of the given
+/// octave and axis. Since `NoiseVersion::V1`, it only depends on the seed,
+/// as hashes like `PermutationHash` would repeat it every 256 octaves.
+pub(crate) fn decorrelation_value<H: LatticeHash>(
+	noise: &Noise<H>,
+	octave: usize,
+	axis: usize,
+	kind: i32,
+) -> f32 {
+	let xs = [DECORRELATION_MARKER, octave as i32, axis as i32, kind];
+	match noise.version {
+		NoiseVersion::V0 => raw_noise_node(noise, &xs),
+		NoiseVersion::V1 => FloatMapping::Portable.map(SplitMixHash.hash(noise.seed, xs)),
+	}
+}
+
+// Hashing an unlikely channel value keeps the decorrelation apart from the noise itself.
+const DECORRELATION_MARKER: i32 = -0x0c7a4e;
+
+/// Rotates and offsets the coordinates of the given octave,
+/// the rotation being a rotation in each plane of two consecutive axes.
+/// Rotating would break the periodicity, so periodic noise is only offset.
+fn decorrelate_octave<H: LatticeHash, C: Coord, const D: usize>(
+	noise: &Noise<H>,
+	octave: usize,
+	xs: &[C; D],
+) -> [C; D] {
+	let mut xs = *xs;
+	let rotated_axes = if noise.is_periodic() { 0..0 } else { 1..D };
+	for axis in rotated_axes {
+		let (sin, cos) = decorrelation_rotation(noise, octave, axis);
+		let (sin, cos) = (C::from_f32(sin), C::from_f32(cos));
+		let (x, y) = (xs[axis - 1], xs[axis]);
+		xs[axis - 1] = x * cos - y * sin;
+		xs[axis] = x * sin + y * cos;
+	}
+	for (axis, x) in xs.iter_mut().enumerate() {
+		let offset = decorrelation_value(noise, octave, axis, 1) * 256.0;
+		*x = *x + C::from_f32(offset);
+	}
+	xs
+}
+
+/// Brings a gradient computed on the coordinates given by `decorrelate_octave`
+/// back to the original coordinates, by applying the inverse rotation.
+fn decorrelate_octave_gradient<H: LatticeHash, const D: usize>(
+	noise: &Noise<H>,
+	octave: usize,
+	gradient: &[f32; D],
+) -> [f32; D] {
+	let mut gradient = *gradient;
+	let rotated_axes = if noise.is_periodic() { 0..0 } else { 1..D };
+	for axis in rotated_axes.rev() {
+		let (sin, cos) = decorrelation_rotation(noise, octave, axis);
+		let (x, y) = (gradient[axis - 1], gradient[axis]);
+		gradient[axis - 1] = x * cos + y * sin;
+		gradient[axis] = -x * sin + y * cos;
+	}
+	gradient
+}
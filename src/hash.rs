@@ -0,0 +1,108 @@
+//! Hashes of lattice nodes, from which all the noise is derived.
+//!
+//! They range from the fast but low quality permutation table to the slower
+//! but well mixed SplitMix, the legacy hash being kept so that existing worlds
+//! do not change.
+
+use crate::mix_bits;
+use std::fmt::Debug;
+
+/// Hash of the integer coordinates of a lattice node (along with whatever
+/// else distinguishes it, like channels), given a seed.
+pub trait LatticeHash: Copy + Debug + Default {
+	fn hash(&self, seed: u64, xs: impl IntoIterator<Item = i32>) -> u64;
+}
+
+/// The original hash of `raw_noise_node`, a mix of xors, shifts and swaps.
+/// It is kept for compatibility and has no quality guarantees.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LegacyHash;
+
+impl LatticeHash for LegacyHash {
+	fn hash(&self, seed: u64, xs: impl IntoIterator<Item = i32>) -> u64 {
+		// The seed is just the starting state of the hash,
+		// so that a zero seed leaves the hash unchanged.
+		let mut a = seed as i32;
+		let mut b = (seed >> 32) as i32;
+		// The arithmetic wraps around (as it always did in release builds),
+		// so that no coordinate can make it overflow.
+		for (i, x) in xs.into_iter().enumerate() {
+			a ^= x;
+			b ^= 17i32
+				.wrapping_mul(i as i32)
+				.wrapping_add(17 * 11)
+				.wrapping_add(x);
+			std::mem::swap(&mut a, &mut b);
+			let shift = (i + 7) % (((b % 11) as usize).saturating_add(5));
+			a ^= a.wrapping_shl(shift as u32);
+		}
+		((a as u32 as u64) << 32) | b as u32 as u64
+	}
+}
+
+/// Each coordinate goes through a full SplitMix64 step, so that every bit
+/// of the input affects every bit of the output. The slowest and the best.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SplitMixHash;
+
+fn splitmix64(state: u64) -> u64 {
+	let mut z = state.wrapping_add(0x9e3779b97f4a7c15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+	z ^ (z >> 31)
+}
+
+impl LatticeHash for SplitMixHash {
+	fn hash(&self, seed: u64, xs: impl IntoIterator<Item = i32>) -> u64 {
+		let mut hash = splitmix64(seed);
+		for x in xs {
+			hash = splitmix64(hash ^ x as u32 as u64);
+		}
+		hash
+	}
+}
+
+/// Lookups in a shuffled table of the bytes, like classic Perlin noise.
+/// The fastest, but only the low byte of each coordinate is used (so the
+/// noise repeats every 256 nodes), there are only 256 different node values
+/// and only 256 different seeds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PermutationHash;
+
+/// The bytes shuffled (with a Fisher-Yates shuffle driven by a fixed LCG),
+/// twice so that an index plus a byte never goes past the end.
+const PERMUTATION: [u8; 512] = {
+	let mut table = [0; 512];
+	let mut i = 0;
+	while i < 256 {
+		table[i] = i as u8;
+		i += 1;
+	}
+	let mut state: u32 = 0x2545f491;
+	let mut i = 255;
+	while i > 0 {
+		state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+		let j = (state >> 8) as usize % (i + 1);
+		let swapped = table[i];
+		table[i] = table[j];
+		table[j] = swapped;
+		i -= 1;
+	}
+	let mut i = 0;
+	while i < 256 {
+		table[256 + i] = table[i];
+		i += 1;
+	}
+	table
+};
+
+impl LatticeHash for PermutationHash {
+	fn hash(&self, seed: u64, xs: impl IntoIterator<Item = i32>) -> u64 {
+		let mut hash = PERMUTATION[mix_bits(seed) as u8 as usize];
+		for x in xs {
+			hash = PERMUTATION[hash as usize + x as u8 as usize];
+		}
+		// Spreads the byte over the whole output for the float mappings.
+		(hash as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15)
+	}
+}
@@ -0,0 +1,99 @@
+//! Heights stored on a square grid, for the algorithms that need the whole
+//! terrain at once (`watershed`, `erosion`) rather than one point at a time.
+
+/// Heights sampled on the same grid as `render_to_file`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heightfield {
+	pub side: u32,
+	/// Row after row.
+	pub heights: Vec<f32>,
+}
+
+impl Heightfield {
+	pub fn sample(side: u32, height: impl Fn(f32, f32) -> f32) -> Heightfield {
+		let mut heights = Vec::with_capacity((side * side) as usize);
+		for py in 0..side {
+			for px in 0..side {
+				heights.push(height(px as f32 / side as f32, py as f32 / side as f32));
+			}
+		}
+		Heightfield { side, heights }
+	}
+
+	/// Samples one of the image generators, the height being the brightness
+	/// of the pixels in [0, 1].
+	pub fn from_generator(side: u32, generator: &dyn Fn(f32, f32) -> image::Rgb<u8>) -> Heightfield {
+		Heightfield::sample(side, |rx, ry| {
+			let [red, green, blue] = generator(rx, ry).0;
+			(red as f32 + green as f32 + blue as f32) / (3.0 * 255.0)
+		})
+	}
+
+	pub fn height(&self, x: u32, y: u32) -> f32 {
+		self.heights[(y * self.side + x) as usize]
+	}
+
+	/// The height between pixels, interpolated bilinearly, along with its
+	/// gradient (in height per pixel). `x` and `y` must be in
+	/// [0, side - 1[ so that the four pixels around them exist.
+	pub fn interpolate(&self, x: f32, y: f32) -> (f32, [f32; 2]) {
+		let (px, py) = (x as u32, y as u32);
+		let (u, v) = (x - px as f32, y - py as f32);
+		let (h00, h10) = (self.height(px, py), self.height(px + 1, py));
+		let (h01, h11) = (self.height(px, py + 1), self.height(px + 1, py + 1));
+		let height = (h00 * (1.0 - u) + h10 * u) * (1.0 - v) + (h01 * (1.0 - u) + h11 * u) * v;
+		let gradient = [
+			(h10 - h00) * (1.0 - v) + (h11 - h01) * v,
+			(h01 - h00) * (1.0 - u) + (h11 - h10) * u,
+		];
+		(height, gradient)
+	}
+
+	/// The neighbors of a pixel, along with their distances to it.
+	pub(crate) fn neighbors(&self, index: usize) -> impl Iterator<Item = (usize, f32)> + '_ {
+		let side = self.side as i64;
+		let (x, y) = (index as i64 % side, index as i64 / side);
+		NEIGHBOR_OFFSETS
+			.iter()
+			.filter(move |(dx, dy)| (0..side).contains(&(x + dx)) && (0..side).contains(&(y + dy)))
+			.map(move |&(dx, dy)| {
+				let distance = if dx != 0 && dy != 0 {
+					std::f32::consts::SQRT_2
+				} else {
+					1.0
+				};
+				(((y + dy) * side + x + dx) as usize, distance)
+			})
+	}
+
+	/// The terrain lit from the top left, with the heights multiplied by
+	/// `relief` (in pixels per unit of height) to tell how steep it looks.
+	pub fn shaded_image(&self, relief: f32) -> image::RgbImage {
+		let light = [-1.0, -1.0, 1.0].map(|x: f32| x / f32::sqrt(3.0));
+		image::ImageBuffer::from_fn(self.side, self.side, |x, y| {
+			// A single pixel has no slope, and no neighbors to measure one.
+			let gradient = if self.side < 2 {
+				[0.0; 2]
+			} else {
+				let (x, y) = (x.min(self.side - 2), y.min(self.side - 2));
+				self.interpolate(x as f32, y as f32).1
+			};
+			let normal = [-gradient[0] * relief, -gradient[1] * relief, 1.0];
+			let length = f32::sqrt(normal.iter().map(|n| n * n).sum());
+			let lighting: f32 = normal.iter().zip(light).map(|(n, l)| n * l).sum();
+			let gray = ((lighting / length).clamp(0.0, 1.0) * 255.0) as u8;
+			image::Rgb([gray, gray, gray])
+		})
+	}
+}
+
+const NEIGHBOR_OFFSETS: [(i64, i64); 8] = [
+	(-1, -1),
+	(0, -1),
+	(1, -1),
+	(-1, 0),
+	(1, 0),
+	(-1, 1),
+	(0, 1),
+	(1, 1),
+];
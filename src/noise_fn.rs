@@ -0,0 +1,563 @@
+//! Noise functions that can be composed, so that a generator can be written
+//! as a chain of combinators on top of `octaves_noise` instead of by hand.
+//!
+//! For example, thresholded noise warped by two other noises:
+//! `octaves(noise, 6, &[3]).scale(10.0).warp([x_noise, y_noise], 1.0).threshold(0.5)`.
+
+use crate::cellular::{cellular_noise, CellularValue, DistanceMetric};
+use crate::fractal::Fbm;
+use crate::warp::{Displacement, Offset, Polar, Warp};
+use crate::{
+	indentity, interpolate, octaves_noise, octaves_noise_multi, LatticeHash, LegacyHash, Noise,
+};
+
+/// A function from points in D dimensions to values (usually in [0, 1]).
+pub trait NoiseFn<const D: usize> {
+	fn sample(&self, xs: &[f32; D]) -> f32;
+
+	/// Samples at `xs * factor`, so a bigger factor gives smaller features.
+	/// The factor can itself be a noise function, sampled at `xs`.
+	fn scale<F: NoiseFn<D>>(self, factor: F) -> Scale<Self, F>
+	where
+		Self: Sized,
+	{
+		Scale { source: self, factor }
+	}
+
+	/// Samples at `xs + offset`.
+	fn translate(self, offset: [f32; D]) -> Translate<Self, D>
+	where
+		Self: Sized,
+	{
+		Translate { source: self, offset }
+	}
+
+	/// Applies `op` to the values.
+	fn map<F: Fn(f32) -> f32>(self, op: F) -> Map<Self, F>
+	where
+		Self: Sized,
+	{
+		Map { source: self, op }
+	}
+
+	/// Combines the values of `self` and `other` with `op`.
+	fn zip<B: NoiseFn<D>, F: Fn(f32, f32) -> f32>(self, other: B, op: F) -> Zip<Self, B, F>
+	where
+		Self: Sized,
+	{
+		Zip { a: self, b: other, op }
+	}
+
+	fn add<B: NoiseFn<D>>(self, other: B) -> Binary<Self, B>
+	where
+		Self: Sized,
+	{
+		Binary { a: self, b: other, op: BinaryOp::Add }
+	}
+
+	fn mul<B: NoiseFn<D>>(self, other: B) -> Binary<Self, B>
+	where
+		Self: Sized,
+	{
+		Binary { a: self, b: other, op: BinaryOp::Mul }
+	}
+
+	fn min<B: NoiseFn<D>>(self, other: B) -> Binary<Self, B>
+	where
+		Self: Sized,
+	{
+		Binary { a: self, b: other, op: BinaryOp::Min }
+	}
+
+	fn max<B: NoiseFn<D>>(self, other: B) -> Binary<Self, B>
+	where
+		Self: Sized,
+	{
+		Binary { a: self, b: other, op: BinaryOp::Max }
+	}
+
+	fn clamp(self, min: f32, max: f32) -> Unary<Self>
+	where
+		Self: Sized,
+	{
+		Unary { source: self, op: UnaryOp::Clamp { min, max } }
+	}
+
+	fn powi(self, exponent: i32) -> Unary<Self>
+	where
+		Self: Sized,
+	{
+		Unary { source: self, op: UnaryOp::Powi { exponent } }
+	}
+
+	/// 0 below `level`, 1 from `level` up.
+	fn threshold(self, level: f32) -> Unary<Self>
+	where
+		Self: Sized,
+	{
+		Unary { source: self, op: UnaryOp::Threshold { level } }
+	}
+
+	/// Maps linearly [`from_inf`, `from_sup`] to [`to_inf`, `to_sup`].
+	fn remap(self, from_inf: f32, from_sup: f32, to_inf: f32, to_sup: f32) -> Unary<Self>
+	where
+		Self: Sized,
+	{
+		let op = UnaryOp::Remap { from_inf, from_sup, to_inf, to_sup };
+		Unary { source: self, op }
+	}
+
+	/// Goes from `self` where `mask` is 0 to `other` where `mask` is 1.
+	fn blend<B: NoiseFn<D>, M: NoiseFn<D>>(self, other: B, mask: M) -> Blend<Self, B, M>
+	where
+		Self: Sized,
+	{
+		Blend { a: self, b: other, mask }
+	}
+
+	/// `self` where `mask` is below `level`, `other` elsewhere.
+	fn select<B: NoiseFn<D>, M: NoiseFn<D>>(
+		self,
+		other: B,
+		mask: M,
+		level: f32,
+	) -> Select<Self, B, M>
+	where
+		Self: Sized,
+	{
+		Select { a: self, b: other, mask, level }
+	}
+
+	/// Samples at `xs` moved on each axis by the value of the field of that
+	/// axis (mapped from [0, 1] to [-1, 1]) times `strength`.
+	/// The fields are either an array of noise functions, or an
+	/// `octaves_multi` that computes all of them at once.
+	fn warp<F: NoiseFnArray<D, D>, S: NoiseFn<D>>(
+		self,
+		fields: F,
+		strength: S,
+	) -> Warp<Self, Offset<F>, S>
+	where
+		Self: Sized,
+	{
+		self.warp_with(Offset { fields }, strength)
+	}
+
+	/// Samples at `xs` moved by `distance` in the direction `angle`
+	/// (in turns, so that noise in [0, 1] points anywhere).
+	fn warp_polar<A, L>(self, angle: A, distance: L) -> Warp<Self, Polar<A, L>, f32>
+	where
+		Self: Sized,
+		Polar<A, L>: Displacement<D>,
+	{
+		self.warp_with(Polar { angle, distance }, 1.0)
+	}
+
+	/// Samples at `xs` moved by `displacement` times `strength`.
+	fn warp_with<W: Displacement<D>, S: NoiseFn<D>>(
+		self,
+		displacement: W,
+		strength: S,
+	) -> Warp<Self, W, S>
+	where
+		Self: Sized,
+	{
+		Warp::new(self, displacement, strength)
+	}
+}
+
+/// Any function of a point is a noise function.
+impl<F: Fn(&[f32; D]) -> f32, const D: usize> NoiseFn<D> for F {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		self(xs)
+	}
+}
+
+/// A constant is a noise function too, handy for combinators that take
+/// noise functions where a constant will do.
+impl<const D: usize> NoiseFn<D> for f32 {
+	fn sample(&self, _xs: &[f32; D]) -> f32 {
+		*self
+	}
+}
+
+/// Noise functions chosen at runtime (see `pattern::Node::build`).
+impl<const D: usize> NoiseFn<D> for Box<dyn NoiseFn<D> + '_> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		(**self).sample(xs)
+	}
+}
+
+/// See `octaves`.
+///
+/// Unlike the other noise functions it does not work in any dimension,
+/// so that the dimension of a whole composition can be inferred.
+#[derive(Clone, Copy, Debug)]
+pub struct Octaves<'a, const D: usize, H: LatticeHash = LegacyHash> {
+	pub noise: &'a Noise<H>,
+	pub octave_count: i32,
+	pub channels: &'a [i32],
+}
+
+/// `octaves_noise` as a noise function.
+pub fn octaves<'a, const D: usize, H: LatticeHash>(
+	noise: &'a Noise<H>,
+	octave_count: i32,
+	channels: &'a [i32],
+) -> Octaves<'a, D, H> {
+	Octaves { noise, octave_count, channels }
+}
+
+impl<H: LatticeHash, const D: usize> NoiseFn<D> for Octaves<'_, D, H> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		octaves_noise(self.noise, self.octave_count, xs, self.channels)
+	}
+}
+
+/// Several noise functions sampled at the same points, K values at a time.
+pub trait NoiseFnArray<const D: usize, const K: usize> {
+	fn sample_all(&self, xs: &[f32; D]) -> [f32; K];
+
+	/// Same as `NoiseFn::scale`, for all the functions at once.
+	fn scale<F: NoiseFn<D>>(self, factor: F) -> Scale<Self, F>
+	where
+		Self: Sized,
+	{
+		Scale { source: self, factor }
+	}
+}
+
+impl<N: NoiseFn<D>, const D: usize, const K: usize> NoiseFnArray<D, K> for [N; K] {
+	fn sample_all(&self, xs: &[f32; D]) -> [f32; K] {
+		self.each_ref().map(|source| source.sample(xs))
+	}
+}
+
+impl<N: NoiseFnArray<D, K>, F: NoiseFn<D>, const D: usize, const K: usize> NoiseFnArray<D, K>
+	for Scale<N, F>
+{
+	fn sample_all(&self, xs: &[f32; D]) -> [f32; K] {
+		let factor = self.factor.sample(xs);
+		self.source.sample_all(&xs.map(|x| x * factor))
+	}
+}
+
+/// See `octaves_multi`.
+#[derive(Clone, Copy, Debug)]
+pub struct OctavesMulti<'a, const D: usize, const K: usize, H: LatticeHash = LegacyHash> {
+	pub noise: &'a Noise<H>,
+	pub octave_count: i32,
+	pub channels: [&'a [i32]; K],
+}
+
+/// `octaves_noise_multi` as K noise functions, which gives the same values as
+/// an array of `octaves` on these channels but walks the lattice only once.
+pub fn octaves_multi<'a, const D: usize, const K: usize, H: LatticeHash>(
+	noise: &'a Noise<H>,
+	octave_count: i32,
+	channels: [&'a [i32]; K],
+) -> OctavesMulti<'a, D, K, H> {
+	OctavesMulti { noise, octave_count, channels }
+}
+
+impl<H: LatticeHash, const D: usize, const K: usize> NoiseFnArray<D, K>
+	for OctavesMulti<'_, D, K, H>
+{
+	fn sample_all(&self, xs: &[f32; D]) -> [f32; K] {
+		octaves_noise_multi(self.noise, self.octave_count, xs, &self.channels)
+	}
+}
+
+/// See `fractal`.
+#[derive(Clone, Debug)]
+pub struct Fractal<'a, const D: usize, H: LatticeHash = LegacyHash> {
+	pub noise: &'a Noise<H>,
+	pub fbm: Fbm,
+	pub channels: &'a [i32],
+}
+
+/// `Fbm::sample` as a noise function.
+pub fn fractal<'a, const D: usize, H: LatticeHash>(
+	noise: &'a Noise<H>,
+	fbm: Fbm,
+	channels: &'a [i32],
+) -> Fractal<'a, D, H> {
+	Fractal { noise, fbm, channels }
+}
+
+impl<H: LatticeHash, const D: usize> NoiseFn<D> for Fractal<'_, D, H> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		self.fbm.sample(self.noise, xs, self.channels)
+	}
+}
+
+/// See `cells`.
+#[derive(Clone, Copy, Debug)]
+pub struct Cells<'a, const D: usize, H: LatticeHash = LegacyHash> {
+	pub noise: &'a Noise<H>,
+	pub channels: &'a [i32],
+	pub metric: DistanceMetric,
+	pub value: CellularValue,
+}
+
+/// `cellular_noise` as a noise function, giving one of its distances.
+pub fn cells<'a, const D: usize, H: LatticeHash>(
+	noise: &'a Noise<H>,
+	channels: &'a [i32],
+	metric: DistanceMetric,
+	value: CellularValue,
+) -> Cells<'a, D, H> {
+	Cells { noise, channels, metric, value }
+}
+
+impl<H: LatticeHash, const D: usize> NoiseFn<D> for Cells<'_, D, H> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		let cells = cellular_noise(self.noise, xs, self.channels, self.metric);
+		self.value.of(&cells)
+	}
+}
+
+/// See `surface`.
+#[derive(Clone, Copy, Debug)]
+pub struct Surface<N, Z> {
+	source: N,
+	height: Z,
+}
+
+/// The 2D function that samples the 3D `source` (one function or several)
+/// at the height given by `height` at the same point.
+pub fn surface<N, Z: NoiseFn<2>>(source: N, height: Z) -> Surface<N, Z> {
+	Surface { source, height }
+}
+
+impl<N: NoiseFn<3>, Z: NoiseFn<2>> NoiseFn<2> for Surface<N, Z> {
+	fn sample(&self, xs: &[f32; 2]) -> f32 {
+		self.source.sample(&[xs[0], xs[1], self.height.sample(xs)])
+	}
+}
+
+impl<N: NoiseFnArray<3, K>, Z: NoiseFn<2>, const K: usize> NoiseFnArray<2, K> for Surface<N, Z> {
+	fn sample_all(&self, xs: &[f32; 2]) -> [f32; K] {
+		self
+			.source
+			.sample_all(&[xs[0], xs[1], self.height.sample(xs)])
+	}
+}
+
+/// A channel of an image as a noise function, with the image stretched over
+/// [0, 1] on both axes and extended by its edges beyond.
+/// Pixels are interpolated bilinearly, so that warping does not show them.
+#[derive(Clone, Copy, Debug)]
+pub struct ImageChannel<'a> {
+	pub image: &'a image::RgbImage,
+	pub channel: usize,
+}
+
+impl NoiseFn<2> for ImageChannel<'_> {
+	fn sample(&self, xs: &[f32; 2]) -> f32 {
+		let (width, height) = self.image.dimensions();
+		// Pixel centers are at half integers.
+		let x = (xs[0] * width as f32 - 0.5).clamp(0.0, (width - 1) as f32);
+		let y = (xs[1] * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+		let (x0, y0) = (x as u32, y as u32);
+		let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+		let pixel = |x, y| self.image.get_pixel(x, y).0[self.channel] as f32 / 255.0;
+		let (fx, fy) = (x.fract(), y.fract());
+		let top = pixel(x0, y0) * (1.0 - fx) + pixel(x1, y0) * fx;
+		let bottom = pixel(x0, y1) * (1.0 - fx) + pixel(x1, y1) * fx;
+		top * (1.0 - fy) + bottom * fy
+	}
+}
+
+/// See `NoiseFn::scale`.
+#[derive(Clone, Copy, Debug)]
+pub struct Scale<N, F = f32> {
+	pub(crate) source: N,
+	pub(crate) factor: F,
+}
+
+impl<N: NoiseFn<D>, F: NoiseFn<D>, const D: usize> NoiseFn<D> for Scale<N, F> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		let factor = self.factor.sample(xs);
+		self.source.sample(&xs.map(|x| x * factor))
+	}
+}
+
+/// See `NoiseFn::translate`.
+#[derive(Clone, Copy, Debug)]
+pub struct Translate<N, const D: usize> {
+	pub(crate) source: N,
+	pub(crate) offset: [f32; D],
+}
+
+impl<N: NoiseFn<D>, const D: usize> NoiseFn<D> for Translate<N, D> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		let mut xs = *xs;
+		xs.iter_mut()
+			.zip(self.offset)
+			.for_each(|(x, offset)| *x += offset);
+		self.source.sample(&xs)
+	}
+}
+
+/// See `NoiseFn::map`.
+#[derive(Clone, Copy, Debug)]
+pub struct Map<N, F> {
+	source: N,
+	op: F,
+}
+
+impl<N: NoiseFn<D>, F: Fn(f32) -> f32, const D: usize> NoiseFn<D> for Map<N, F> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		(self.op)(self.source.sample(xs))
+	}
+}
+
+/// See `NoiseFn::zip`.
+#[derive(Clone, Copy, Debug)]
+pub struct Zip<A, B, F> {
+	a: A,
+	b: B,
+	op: F,
+}
+
+impl<A: NoiseFn<D>, B: NoiseFn<D>, F: Fn(f32, f32) -> f32, const D: usize> NoiseFn<D>
+	for Zip<A, B, F>
+{
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		(self.op)(self.a.sample(xs), self.b.sample(xs))
+	}
+}
+
+/// The operations of `NoiseFn::add`, `mul`, `min` and `max`.
+/// Unlike the closures of `NoiseFn::zip` they can be described
+/// (see `pattern::Describe`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryOp {
+	Add,
+	Mul,
+	Min,
+	Max,
+}
+
+impl BinaryOp {
+	pub fn apply(self, a: f32, b: f32) -> f32 {
+		match self {
+			BinaryOp::Add => a + b,
+			BinaryOp::Mul => a * b,
+			BinaryOp::Min => a.min(b),
+			BinaryOp::Max => a.max(b),
+		}
+	}
+}
+
+/// See `BinaryOp`.
+#[derive(Clone, Copy, Debug)]
+pub struct Binary<A, B> {
+	pub(crate) a: A,
+	pub(crate) b: B,
+	pub(crate) op: BinaryOp,
+}
+
+impl<A: NoiseFn<D>, B: NoiseFn<D>, const D: usize> NoiseFn<D> for Binary<A, B> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		self.op.apply(self.a.sample(xs), self.b.sample(xs))
+	}
+}
+
+/// The operations of `NoiseFn::clamp`, `powi`, `threshold` and `remap`.
+/// Unlike the closures of `NoiseFn::map` they can be described
+/// (see `pattern::Describe`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnaryOp {
+	Clamp {
+		min: f32,
+		max: f32,
+	},
+	Powi {
+		exponent: i32,
+	},
+	Threshold {
+		level: f32,
+	},
+	Remap {
+		from_inf: f32,
+		from_sup: f32,
+		to_inf: f32,
+		to_sup: f32,
+	},
+}
+
+impl UnaryOp {
+	pub fn apply(self, value: f32) -> f32 {
+		match self {
+			UnaryOp::Clamp { min, max } => value.clamp(min, max),
+			UnaryOp::Powi { exponent } => value.powi(exponent),
+			UnaryOp::Threshold { level } => {
+				if value < level {
+					0.0
+				} else {
+					1.0
+				}
+			},
+			UnaryOp::Remap { from_inf, from_sup, to_inf, to_sup } => {
+				interpolate(&indentity, value, from_inf, from_sup, to_inf, to_sup)
+			},
+		}
+	}
+}
+
+/// See `UnaryOp`.
+#[derive(Clone, Copy, Debug)]
+pub struct Unary<N> {
+	pub(crate) source: N,
+	pub(crate) op: UnaryOp,
+}
+
+impl<N: NoiseFn<D>, const D: usize> NoiseFn<D> for Unary<N> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		self.op.apply(self.source.sample(xs))
+	}
+}
+
+/// See `NoiseFn::blend`.
+#[derive(Clone, Copy, Debug)]
+pub struct Blend<A, B, M> {
+	pub(crate) a: A,
+	pub(crate) b: B,
+	pub(crate) mask: M,
+}
+
+impl<A: NoiseFn<D>, B: NoiseFn<D>, M: NoiseFn<D>, const D: usize> NoiseFn<D> for Blend<A, B, M> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		let mask = self.mask.sample(xs);
+		interpolate(
+			&indentity,
+			mask,
+			0.0,
+			1.0,
+			self.a.sample(xs),
+			self.b.sample(xs),
+		)
+	}
+}
+
+/// See `NoiseFn::select`.
+#[derive(Clone, Copy, Debug)]
+pub struct Select<A, B, M> {
+	pub(crate) a: A,
+	pub(crate) b: B,
+	pub(crate) mask: M,
+	pub(crate) level: f32,
+}
+
+impl<A: NoiseFn<D>, B: NoiseFn<D>, M: NoiseFn<D>, const D: usize> NoiseFn<D> for Select<A, B, M> {
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		// Only the selected side is sampled.
+		if self.mask.sample(xs) < self.level {
+			self.a.sample(xs)
+		} else {
+			self.b.sample(xs)
+		}
+	}
+}
@@ -0,0 +1,699 @@
+//! Patterns as text files: a graph of noise functions and the way it is
+//! colored, in JSON, so that a pattern can be shared and rendered without
+//! recompiling (see `noizebra render`).
+//!
+//! Compositions of noise functions can be dumped to the same format with
+//! `Describe`, as long as they do not contain closures (`NoiseFn::map` and
+//! `NoiseFn::zip`), which cannot be described.
+
+use crate::cellular::{CellularValue, DistanceMetric};
+use crate::fractal::Fbm;
+use crate::noise_fn::{
+	cells, octaves, Binary, BinaryOp, Blend, Cells, Fractal, NoiseFn, Octaves, OctavesMulti, Scale,
+	Select, Translate, Unary, UnaryOp,
+};
+use crate::warp::{Offset, Polar, Warp};
+use crate::{
+	Basis, FloatMapping, LatticeHash, LegacyHash, Noise, NoiseVersion, PermutationHash, SplitMixHash,
+};
+use serde::de::Error;
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU32;
+
+/// A noise function in 2D, a constant being written as just a number.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Node {
+	Constant(f32),
+	Function(Box<Function>),
+}
+
+/// The noise functions of `noise_fn` and `warp`, with the same names and
+/// parameters, written as `{ "type": "scale", "source": ..., "factor": 10 }`.
+/// The leaves, which sample the noise, also take the `NoiseSettings` that
+/// they change.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Function {
+	Octaves {
+		octave_count: i32,
+		channels: Vec<i32>,
+		#[serde(default, skip_serializing_if = "NoiseSettings::is_empty")]
+		noise: NoiseSettings,
+	},
+	Fractal {
+		fbm: Fbm,
+		channels: Vec<i32>,
+		#[serde(default, skip_serializing_if = "NoiseSettings::is_empty")]
+		noise: NoiseSettings,
+	},
+	Cells {
+		channels: Vec<i32>,
+		#[serde(default)]
+		metric: DistanceMetric,
+		value: CellularValue,
+		#[serde(default, skip_serializing_if = "NoiseSettings::is_empty")]
+		noise: NoiseSettings,
+	},
+	Scale {
+		source: Node,
+		factor: Node,
+	},
+	Translate {
+		source: Node,
+		offset: [f32; 2],
+	},
+	Add {
+		a: Node,
+		b: Node,
+	},
+	Mul {
+		a: Node,
+		b: Node,
+	},
+	Min {
+		a: Node,
+		b: Node,
+	},
+	Max {
+		a: Node,
+		b: Node,
+	},
+	Clamp {
+		source: Node,
+		min: f32,
+		max: f32,
+	},
+	Powi {
+		source: Node,
+		exponent: i32,
+	},
+	Threshold {
+		source: Node,
+		level: f32,
+	},
+	Remap {
+		source: Node,
+		from_inf: f32,
+		from_sup: f32,
+		to_inf: f32,
+		to_sup: f32,
+	},
+	Blend {
+		a: Node,
+		b: Node,
+		mask: Node,
+	},
+	Select {
+		a: Node,
+		b: Node,
+		mask: Node,
+		level: f32,
+	},
+	/// `samples` is the number of samples of the line integral,
+	/// 1 (the default) for a plain warp.
+	Warp {
+		source: Node,
+		fields: [Node; 2],
+		strength: Node,
+		#[serde(default = "one_sample", skip_serializing_if = "is_one_sample")]
+		samples: usize,
+	},
+	WarpPolar {
+		source: Node,
+		angle: Node,
+		distance: Node,
+		#[serde(default = "unit_strength", skip_serializing_if = "is_unit_strength")]
+		strength: Node,
+		#[serde(default = "one_sample", skip_serializing_if = "is_one_sample")]
+		samples: usize,
+	},
+}
+
+fn one_sample() -> usize {
+	1
+}
+
+fn is_one_sample(samples: &usize) -> bool {
+	*samples == 1
+}
+
+fn unit_strength() -> Node {
+	Node::Constant(1.0)
+}
+
+fn is_unit_strength(strength: &Node) -> bool {
+	*strength == unit_strength()
+}
+
+/// The `LatticeHash` of a noise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashKind {
+	Legacy,
+	SplitMix,
+	Permutation,
+}
+
+impl From<LegacyHash> for HashKind {
+	fn from(_hash: LegacyHash) -> HashKind {
+		HashKind::Legacy
+	}
+}
+
+impl From<SplitMixHash> for HashKind {
+	fn from(_hash: SplitMixHash) -> HashKind {
+		HashKind::SplitMix
+	}
+}
+
+impl From<PermutationHash> for HashKind {
+	fn from(_hash: PermutationHash) -> HashKind {
+		HashKind::Permutation
+	}
+}
+
+/// The settings of the noise sampled by a leaf of the graph (`octaves`,
+/// `fractal` or `cells`) that differ from the noise the pattern is rendered
+/// with, that is `Noise::new` with the seed of the pattern and the options
+/// of the command line.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoiseSettings {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub version: Option<NoiseVersion>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub basis: Option<Basis>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub hash: Option<HashKind>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub float_mapping: Option<FloatMapping>,
+	/// The periods on the two axes, `null` on an axis that does not wrap.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub periods: Option<[Option<NonZeroU32>; 2]>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub decorrelate_octaves: Option<bool>,
+}
+
+impl NoiseSettings {
+	/// The settings of `noise` that differ from the ones of `Noise::new`.
+	pub fn of<H: LatticeHash + Into<HashKind>>(noise: &Noise<H>) -> NoiseSettings {
+		fn changed<T: PartialEq>(value: T, default: T) -> Option<T> {
+			(value != default).then_some(value)
+		}
+		let default = Noise::new(noise.seed);
+		NoiseSettings {
+			version: changed(noise.version, default.version),
+			basis: changed(noise.basis, default.basis),
+			hash: changed(noise.hash.into(), default.hash.into()),
+			float_mapping: changed(noise.float_mapping, default.float_mapping),
+			periods: changed(
+				[noise.periods[0], noise.periods[1]],
+				[default.periods[0], default.periods[1]],
+			),
+			decorrelate_octaves: changed(noise.decorrelate_octaves, default.decorrelate_octaves),
+		}
+	}
+
+	fn is_empty(&self) -> bool {
+		*self == NoiseSettings::default()
+	}
+
+	/// Makes the lattice wrap around on the given axes every `scale` units,
+	/// the width of the image at the scale of the noise.
+	fn tile(&mut self, axes: [bool; 2], scale: f32) -> Result<(), String> {
+		if self.basis == Some(Basis::Simplex) {
+			return Err("simplex noise cannot tile".to_string());
+		}
+		if scale < 1.0 || scale.fract() != 0.0 {
+			return Err(format!(
+				"cannot tile noise at the scale {scale}, which is not a positive integer"
+			));
+		}
+		let periods = self.periods.get_or_insert([None, None]);
+		for (period, tiled) in periods.iter_mut().zip(axes) {
+			if tiled {
+				*period = NonZeroU32::new(scale as u32);
+			}
+		}
+		Ok(())
+	}
+
+	/// `noise` changed by these settings, but for the hash which is a type
+	/// (see `Function::build`).
+	fn apply<H: LatticeHash>(&self, noise: &Noise<H>) -> Noise<H> {
+		let mut noise = *noise;
+		if let Some(version) = self.version {
+			noise.version = version;
+		}
+		if let Some(basis) = self.basis {
+			noise.basis = basis;
+		}
+		if let Some(float_mapping) = self.float_mapping {
+			noise.float_mapping = float_mapping;
+		}
+		if let Some([x_period, y_period]) = self.periods {
+			noise.periods[0] = x_period;
+			noise.periods[1] = y_period;
+		}
+		if let Some(decorrelate_octaves) = self.decorrelate_octaves {
+			noise.decorrelate_octaves = decorrelate_octaves;
+		}
+		noise
+	}
+}
+
+impl Node {
+	/// The noise function described by the node, sampling `noise`.
+	pub fn build<'a, H: LatticeHash>(&'a self, noise: &'a Noise<H>) -> Box<dyn NoiseFn<2> + 'a> {
+		match self {
+			Node::Constant(value) => Box::new(*value),
+			Node::Function(function) => function.build(noise),
+		}
+	}
+
+	fn function(function: Function) -> Node {
+		Node::Function(Box::new(function))
+	}
+
+	/// See `Pattern::tiled`, `scale` being the scale of the image at this node.
+	fn tile(&mut self, axes: [bool; 2], scale: f32) -> Result<(), String> {
+		match self {
+			Node::Constant(_) => Ok(()),
+			Node::Function(function) => function.tile(axes, scale),
+		}
+	}
+}
+
+impl Function {
+	fn build<'a, H: LatticeHash>(&'a self, noise: &'a Noise<H>) -> Box<dyn NoiseFn<2> + 'a> {
+		match self {
+			Function::Octaves { noise: settings, .. }
+			| Function::Fractal { noise: settings, .. }
+			| Function::Cells { noise: settings, .. } => {
+				let noise = settings.apply(noise);
+				match settings.hash {
+					None => self.build_leaf(noise),
+					Some(HashKind::Legacy) => self.build_leaf(noise.with_hash(LegacyHash)),
+					Some(HashKind::SplitMix) => self.build_leaf(noise.with_hash(SplitMixHash)),
+					Some(HashKind::Permutation) => self.build_leaf(noise.with_hash(PermutationHash)),
+				}
+			},
+			Function::Scale { source, factor } => {
+				Box::new(source.build(noise).scale(factor.build(noise)))
+			},
+			Function::Translate { source, offset } => Box::new(source.build(noise).translate(*offset)),
+			Function::Add { a, b } => Box::new(a.build(noise).add(b.build(noise))),
+			Function::Mul { a, b } => Box::new(a.build(noise).mul(b.build(noise))),
+			Function::Min { a, b } => Box::new(a.build(noise).min(b.build(noise))),
+			Function::Max { a, b } => Box::new(a.build(noise).max(b.build(noise))),
+			Function::Clamp { source, min, max } => Box::new(source.build(noise).clamp(*min, *max)),
+			Function::Powi { source, exponent } => Box::new(source.build(noise).powi(*exponent)),
+			Function::Threshold { source, level } => Box::new(source.build(noise).threshold(*level)),
+			Function::Remap { source, from_inf, from_sup, to_inf, to_sup } => Box::new(
+				source
+					.build(noise)
+					.remap(*from_inf, *from_sup, *to_inf, *to_sup),
+			),
+			Function::Blend { a, b, mask } => {
+				Box::new(a.build(noise).blend(b.build(noise), mask.build(noise)))
+			},
+			Function::Select { a, b, mask, level } => Box::new(a.build(noise).select(
+				b.build(noise),
+				mask.build(noise),
+				*level,
+			)),
+			Function::Warp { source, fields, strength, samples } => {
+				let fields = fields.each_ref().map(|field| field.build(noise));
+				let warp = source.build(noise).warp(fields, strength.build(noise));
+				with_samples(warp, *samples)
+			},
+			Function::WarpPolar { source, angle, distance, strength, samples } => {
+				let polar = Polar {
+					angle: angle.build(noise),
+					distance: distance.build(noise),
+				};
+				let warp = source.build(noise).warp_with(polar, strength.build(noise));
+				with_samples(warp, *samples)
+			},
+		}
+	}
+
+	fn tile(&mut self, axes: [bool; 2], scale: f32) -> Result<(), String> {
+		match self {
+			Function::Octaves { noise, .. }
+			| Function::Fractal { noise, .. }
+			| Function::Cells { noise, .. } => noise.tile(axes, scale),
+			Function::Scale { source, factor: Node::Constant(factor) } => {
+				source.tile(axes, scale * *factor)
+			},
+			Function::Scale { .. } => Err("cannot tile a scale by a noise function".to_string()),
+			Function::Translate { source, .. }
+			| Function::Clamp { source, .. }
+			| Function::Powi { source, .. }
+			| Function::Threshold { source, .. }
+			| Function::Remap { source, .. } => source.tile(axes, scale),
+			Function::Add { a, b }
+			| Function::Mul { a, b }
+			| Function::Min { a, b }
+			| Function::Max { a, b } => [a, b]
+				.into_iter()
+				.try_for_each(|node| node.tile(axes, scale)),
+			Function::Blend { a, b, mask } | Function::Select { a, b, mask, .. } => [a, b, mask]
+				.into_iter()
+				.try_for_each(|node| node.tile(axes, scale)),
+			// The displacements are periodic too, so the source is sampled
+			// at the same place on both sides of the tile.
+			Function::Warp { source, fields, strength, .. } => {
+				let [x_field, y_field] = fields;
+				[source, x_field, y_field, strength]
+					.into_iter()
+					.try_for_each(|node| node.tile(axes, scale))
+			},
+			Function::WarpPolar { source, angle, distance, strength, .. } => {
+				[source, angle, distance, strength]
+					.into_iter()
+					.try_for_each(|node| node.tile(axes, scale))
+			},
+		}
+	}
+
+	/// The noise function of a leaf, which owns the noise it samples
+	/// since its settings may differ from the ones given to `build`.
+	fn build_leaf<'a, H: LatticeHash + 'a>(&'a self, noise: Noise<H>) -> Box<dyn NoiseFn<2> + 'a> {
+		match self {
+			Function::Octaves { octave_count, channels, .. } => {
+				Box::new(move |xs: &[f32; 2]| octaves(&noise, *octave_count, channels).sample(xs))
+			},
+			Function::Fractal { fbm, channels, .. } => {
+				Box::new(move |xs: &[f32; 2]| fbm.sample(&noise, xs, channels))
+			},
+			Function::Cells { channels, metric, value, .. } => {
+				Box::new(move |xs: &[f32; 2]| cells(&noise, channels, *metric, *value).sample(xs))
+			},
+			_ => unreachable!("{self:?} is not a leaf"),
+		}
+	}
+}
+
+fn with_samples<'a, N, W, S>(warp: Warp<N, W, S>, samples: usize) -> Box<dyn NoiseFn<2> + 'a>
+where
+	Warp<N, W, S>: NoiseFn<2> + 'a,
+{
+	if samples > 1 {
+		Box::new(warp.line_integral(samples))
+	} else {
+		Box::new(warp)
+	}
+}
+
+/// A color of a gradient, at the value `at`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColorStop {
+	pub at: f32,
+	pub color: [u8; 3],
+}
+
+/// How the values of the noise become pixels.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Color {
+	/// From black at 0 to white at 1.
+	Gray { value: Node },
+	/// One node per channel, each from 0 to 1.
+	Rgb { red: Node, green: Node, blue: Node },
+	/// Linear interpolation between the stops, and the color of the
+	/// nearest stop beyond them.
+	Gradient { value: Node, stops: Vec<ColorStop> },
+}
+
+impl Color {
+	pub fn gray(value: &impl Describe) -> Color {
+		Color::Gray { value: value.describe() }
+	}
+
+	pub fn rgb(red: &impl Describe, green: &impl Describe, blue: &impl Describe) -> Color {
+		Color::Rgb {
+			red: red.describe(),
+			green: green.describe(),
+			blue: blue.describe(),
+		}
+	}
+}
+
+/// Everything needed to render an image, but the noise settings that the
+/// leaves leave to the noise it is rendered with (see `NoiseSettings`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pattern {
+	/// Replaces the seed of the noise when given.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub seed: Option<u64>,
+	pub color: Color,
+}
+
+impl Pattern {
+	pub fn from_json(text: &str) -> serde_json::Result<Pattern> {
+		let pattern: Pattern = serde_json::from_str(text)?;
+		if let Color::Gradient { stops, .. } = &pattern.color {
+			if stops.is_empty() {
+				return Err(serde_json::Error::custom(
+					"a gradient needs at least one stop",
+				));
+			}
+		}
+		Ok(pattern)
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap()
+	}
+
+	/// The same pattern with the noise of every leaf wrapping around on the
+	/// given axes (x then y), so that the image tiles seamlessly on them.
+	///
+	/// The leaves must see the image at an integer scale, that is through
+	/// `scale` nodes by constants that multiply to an integer, and must not
+	/// be simplex noise, which has no periodic lattice.
+	pub fn tiled(&self, axes: [bool; 2]) -> Result<Pattern, String> {
+		let mut pattern = self.clone();
+		let nodes = match &mut pattern.color {
+			Color::Gray { value } | Color::Gradient { value, .. } => vec![value],
+			Color::Rgb { red, green, blue } => vec![red, green, blue],
+		};
+		for node in nodes {
+			node.tile(axes, 1.0)?;
+		}
+		Ok(pattern)
+	}
+
+	/// The color of each point, `noise` being used as is
+	/// (the seed of the pattern is up to the caller).
+	pub fn build<'a, H: LatticeHash>(
+		&'a self,
+		noise: &'a Noise<H>,
+	) -> impl Fn(f32, f32) -> image::Rgb<u8> + 'a {
+		let nodes = match &self.color {
+			Color::Gray { value } | Color::Gradient { value, .. } => vec![value],
+			Color::Rgb { red, green, blue } => vec![red, green, blue],
+		};
+		let values: Vec<_> = nodes.into_iter().map(|node| node.build(noise)).collect();
+		let mut stops = match &self.color {
+			Color::Gradient { stops, .. } => stops.clone(),
+			_ => Vec::new(),
+		};
+		stops.sort_by(|a, b| a.at.total_cmp(&b.at));
+		let to_u8 = |value: f32| (value * 255.0) as u8;
+		move |rx, ry| {
+			let sample = |i: usize| values[i].sample(&[rx, ry]);
+			match self.color {
+				Color::Gray { .. } => {
+					let gray = to_u8(sample(0));
+					image::Rgb([gray, gray, gray])
+				},
+				Color::Rgb { .. } => image::Rgb([0, 1, 2].map(|i| to_u8(sample(i)))),
+				Color::Gradient { .. } => gradient_color(&stops, sample(0)),
+			}
+		}
+	}
+}
+
+/// `stops` are sorted and not empty.
+fn gradient_color(stops: &[ColorStop], value: f32) -> image::Rgb<u8> {
+	let after = stops.partition_point(|stop| stop.at <= value);
+	let color = if after == 0 {
+		stops[0].color
+	} else if after == stops.len() {
+		stops[stops.len() - 1].color
+	} else {
+		let (inf, sup) = (&stops[after - 1], &stops[after]);
+		let ratio = (value - inf.at) / (sup.at - inf.at);
+		let mut color = [0; 3];
+		for (i, channel) in color.iter_mut().enumerate() {
+			let (from, to) = (inf.color[i] as f32, sup.color[i] as f32);
+			*channel = (from + (to - from) * ratio) as u8;
+		}
+		color
+	};
+	image::Rgb(color)
+}
+
+/// Noise functions that can be turned back into a `Node`.
+pub trait Describe {
+	fn describe(&self) -> Node;
+}
+
+/// Same as `Describe` for the K noise functions of a `NoiseFnArray`,
+/// as K separate nodes.
+pub trait DescribeArray<const K: usize> {
+	fn describe_all(&self) -> [Node; K];
+}
+
+impl<N: Describe, const K: usize> DescribeArray<K> for [N; K] {
+	fn describe_all(&self) -> [Node; K] {
+		self.each_ref().map(N::describe)
+	}
+}
+
+impl<H: LatticeHash + Into<HashKind>, const K: usize> DescribeArray<K>
+	for OctavesMulti<'_, 2, K, H>
+{
+	fn describe_all(&self) -> [Node; K] {
+		self
+			.channels
+			.map(|channels| octaves::<2, H>(self.noise, self.octave_count, channels).describe())
+	}
+}
+
+impl<N: DescribeArray<K>, F: Describe, const K: usize> DescribeArray<K> for Scale<N, F> {
+	fn describe_all(&self) -> [Node; K] {
+		self
+			.source
+			.describe_all()
+			.map(|source| Node::function(Function::Scale { source, factor: self.factor.describe() }))
+	}
+}
+
+impl Describe for f32 {
+	fn describe(&self) -> Node {
+		Node::Constant(*self)
+	}
+}
+
+/// The seed of the noise is left to the pattern.
+impl<H: LatticeHash + Into<HashKind>> Describe for Octaves<'_, 2, H> {
+	fn describe(&self) -> Node {
+		Node::function(Function::Octaves {
+			octave_count: self.octave_count,
+			channels: self.channels.to_vec(),
+			noise: NoiseSettings::of(self.noise),
+		})
+	}
+}
+
+impl<H: LatticeHash + Into<HashKind>> Describe for Fractal<'_, 2, H> {
+	fn describe(&self) -> Node {
+		Node::function(Function::Fractal {
+			fbm: self.fbm.clone(),
+			channels: self.channels.to_vec(),
+			noise: NoiseSettings::of(self.noise),
+		})
+	}
+}
+
+impl<H: LatticeHash + Into<HashKind>> Describe for Cells<'_, 2, H> {
+	fn describe(&self) -> Node {
+		Node::function(Function::Cells {
+			channels: self.channels.to_vec(),
+			metric: self.metric,
+			value: self.value,
+			noise: NoiseSettings::of(self.noise),
+		})
+	}
+}
+
+impl<N: Describe, F: Describe> Describe for Scale<N, F> {
+	fn describe(&self) -> Node {
+		Node::function(Function::Scale {
+			source: self.source.describe(),
+			factor: self.factor.describe(),
+		})
+	}
+}
+
+impl<N: Describe> Describe for Translate<N, 2> {
+	fn describe(&self) -> Node {
+		Node::function(Function::Translate {
+			source: self.source.describe(),
+			offset: self.offset,
+		})
+	}
+}
+
+impl<A: Describe, B: Describe> Describe for Binary<A, B> {
+	fn describe(&self) -> Node {
+		let (a, b) = (self.a.describe(), self.b.describe());
+		Node::function(match self.op {
+			BinaryOp::Add => Function::Add { a, b },
+			BinaryOp::Mul => Function::Mul { a, b },
+			BinaryOp::Min => Function::Min { a, b },
+			BinaryOp::Max => Function::Max { a, b },
+		})
+	}
+}
+
+impl<N: Describe> Describe for Unary<N> {
+	fn describe(&self) -> Node {
+		let source = self.source.describe();
+		Node::function(match self.op {
+			UnaryOp::Clamp { min, max } => Function::Clamp { source, min, max },
+			UnaryOp::Powi { exponent } => Function::Powi { source, exponent },
+			UnaryOp::Threshold { level } => Function::Threshold { source, level },
+			UnaryOp::Remap { from_inf, from_sup, to_inf, to_sup } => {
+				Function::Remap { source, from_inf, from_sup, to_inf, to_sup }
+			},
+		})
+	}
+}
+
+impl<A: Describe, B: Describe, M: Describe> Describe for Blend<A, B, M> {
+	fn describe(&self) -> Node {
+		Node::function(Function::Blend {
+			a: self.a.describe(),
+			b: self.b.describe(),
+			mask: self.mask.describe(),
+		})
+	}
+}
+
+impl<A: Describe, B: Describe, M: Describe> Describe for Select<A, B, M> {
+	fn describe(&self) -> Node {
+		Node::function(Function::Select {
+			a: self.a.describe(),
+			b: self.b.describe(),
+			mask: self.mask.describe(),
+			level: self.level,
+		})
+	}
+}
+
+impl<N: Describe, F: DescribeArray<2>, S: Describe> Describe for Warp<N, Offset<F>, S> {
+	fn describe(&self) -> Node {
+		Node::function(Function::Warp {
+			source: self.source.describe(),
+			fields: self.displacement.fields.describe_all(),
+			strength: self.strength.describe(),
+			samples: self.samples,
+		})
+	}
+}
+
+impl<N: Describe, A: Describe, L: Describe, S: Describe> Describe for Warp<N, Polar<A, L>, S> {
+	fn describe(&self) -> Node {
+		Node::function(Function::WarpPolar {
+			source: self.source.describe(),
+			angle: self.displacement.angle.describe(),
+			distance: self.displacement.distance.describe(),
+			strength: self.strength.describe(),
+			samples: self.samples,
+		})
+	}
+}
@@ -0,0 +1,265 @@
+//! Statistical quality checks of the lattice hashes and of the noise,
+//! so that a change to a hash can be judged on numbers.
+//!
+//! Run with `cargo test quality -- --nocapture` to see the measures.
+
+use crate::{
+	octaves_noise, raw_noise_node, FloatMapping, LatticeHash, Noise, PermutationHash, SplitMixHash,
+};
+
+const SIDE: i32 = 1000;
+
+/// Chi-square statistic of the node values over `SIDE * SIDE` nodes,
+/// against the uniform distribution on `bins` bins.
+fn node_chi_square<H: LatticeHash>(noise: &Noise<H>, bins: usize) -> f64 {
+	let mut counts = vec![0u32; bins];
+	for y in 0..SIDE {
+		for x in 0..SIDE {
+			let value = raw_noise_node(noise, &[x - SIDE / 2, y - SIDE / 2]);
+			counts[((value * bins as f32) as usize).min(bins - 1)] += 1;
+		}
+	}
+	let expected = (SIDE * SIDE) as f64 / bins as f64;
+	counts
+		.iter()
+		.map(|&count| (count as f64 - expected).powi(2) / expected)
+		.sum()
+}
+
+/// The 24 bits of precision of a node value.
+fn node_bits<H: LatticeHash>(noise: &Noise<H>, xs: &[i32]) -> u32 {
+	(raw_noise_node(noise, xs) * (1 << 24) as f32) as u32
+}
+
+/// The bits of the node values that flip when going from a node to the node
+/// `step` away, for `SIDE * SIDE` nodes (the last coordinate being a channel).
+fn flips<H: LatticeHash>(noise: &Noise<H>, step: [i32; 3]) -> Vec<u32> {
+	let mut flips = Vec::with_capacity((SIDE * SIDE) as usize);
+	for y in 0..SIDE {
+		for x in 0..SIDE {
+			let node = [x - SIDE / 2, y - SIDE / 2, 1];
+			let neighbour = [node[0] + step[0], node[1] + step[1], node[2] + step[2]];
+			flips.push(node_bits(noise, &node) ^ node_bits(noise, &neighbour));
+		}
+	}
+	flips
+}
+
+/// Probability of each of the 24 bits to flip.
+fn flip_rates(flips: &[u32]) -> [f64; 24] {
+	let mut counts = [0u32; 24];
+	for &changed in flips {
+		for (bit, count) in counts.iter_mut().enumerate() {
+			*count += (changed >> bit) & 1;
+		}
+	}
+	counts.map(|count| count as f64 / flips.len() as f64)
+}
+
+/// Worst distance to 1/2 of the flip rates, which is 0 for a perfect
+/// avalanche and 1/2 for a bit that never (or always) flips.
+fn worst_flip_bias(rates: &[f64; 24]) -> f64 {
+	rates
+		.iter()
+		.map(|rate| (rate - 0.5).abs())
+		.fold(0.0, f64::max)
+}
+
+/// Worst correlation between the flips of two different bits, which is 0 when
+/// the bits flip independently (the bit independence criterion) and 1 when
+/// they always flip together (or never do).
+fn worst_flip_correlation(flips: &[u32]) -> f64 {
+	// The flips of each bit as a bit set, so that the flips of two bits
+	// can be counted together 64 nodes at a time.
+	let mut bit_flips = vec![vec![0u64; flips.len().div_ceil(64)]; 24];
+	for (index, &changed) in flips.iter().enumerate() {
+		for (bit, bit_flips) in bit_flips.iter_mut().enumerate() {
+			bit_flips[index / 64] |= (((changed >> bit) & 1) as u64) << (index % 64);
+		}
+	}
+	let rates = flip_rates(flips);
+	let mut worst = 0.0f64;
+	for a in 0..24 {
+		for b in a + 1..24 {
+			let both_count: u32 = bit_flips[a]
+				.iter()
+				.zip(&bit_flips[b])
+				.map(|(a, b)| (a & b).count_ones())
+				.sum();
+			let both = both_count as f64 / flips.len() as f64;
+			let deviations = rates[a] * (1.0 - rates[a]) * rates[b] * (1.0 - rates[b]);
+			let correlation = (both - rates[a] * rates[b]) / f64::sqrt(deviations);
+			// Bits that never flip are caught by the flip bias.
+			if correlation.is_finite() {
+				worst = worst.max(correlation.abs());
+			}
+		}
+	}
+	worst
+}
+
+/// Pearson correlation between the samples of `a` and of `b`.
+fn correlation(a: &[f32], b: &[f32]) -> f64 {
+	let n = a.len() as f64;
+	let mean_a = a.iter().map(|&x| x as f64).sum::<f64>() / n;
+	let mean_b = b.iter().map(|&x| x as f64).sum::<f64>() / n;
+	let mut covariance = 0.0;
+	let mut variance_a = 0.0;
+	let mut variance_b = 0.0;
+	for (&x, &y) in a.iter().zip(b) {
+		let (dx, dy) = (x as f64 - mean_a, y as f64 - mean_b);
+		covariance += dx * dy;
+		variance_a += dx * dx;
+		variance_b += dy * dy;
+	}
+	covariance / f64::sqrt(variance_a * variance_b)
+}
+
+/// Samples `octaves_noise` on a grid that does not line up with the lattice.
+fn octaves_samples<H: LatticeHash>(noise: &Noise<H>, octave_count: i32, channel: i32) -> Vec<f32> {
+	let side = 300;
+	let mut samples = Vec::with_capacity(side * side);
+	for y in 0..side {
+		for x in 0..side {
+			let xs = [x as f32 * 0.37 + 0.1, y as f32 * 0.37 + 0.2];
+			samples.push(octaves_noise(noise, octave_count, &xs, &[channel]));
+		}
+	}
+	samples
+}
+
+/// Bounds on the measures of `check_hash`.
+struct Bounds {
+	chi_square: f64,
+	flip_bias: f64,
+	flip_correlation: f64,
+}
+
+/// The bounds of a hash of good quality. With 100 bins, the chi-square of truly
+/// uniform values is about 99 ± 14, and over `SIDE * SIDE` nodes the flip
+/// biases and correlations of independent bits are about 0.001.
+const GOOD_QUALITY: Bounds = Bounds {
+	chi_square: 200.0,
+	flip_bias: 0.01,
+	flip_correlation: 0.01,
+};
+
+fn check_hash<H: LatticeHash>(noise: &Noise<H>, bounds: &Bounds) {
+	let chi_square = node_chi_square(noise, 100);
+	println!("{noise:?}");
+	println!("  chi-square on 100 bins: {chi_square:.1}");
+	assert!(chi_square < bounds.chi_square, "{chi_square}");
+	let steps = [
+		("x + 1", [1, 0, 0]),
+		("y + 1", [0, 1, 0]),
+		("channel + 1", [0, 0, 1]),
+	];
+	for (name, step) in steps {
+		let flips = flips(noise, step);
+		let rates = flip_rates(&flips);
+		let mean = rates.iter().sum::<f64>() / rates.len() as f64;
+		let bias = worst_flip_bias(&rates);
+		let correlation = worst_flip_correlation(&flips);
+		println!(
+			"  {name}: mean flip rate {mean:.4}, worst bit bias {bias:.4}, \
+			 worst flip correlation {correlation:.4}"
+		);
+		assert!(bias < bounds.flip_bias, "{name} {bias}");
+		assert!(
+			correlation < bounds.flip_correlation,
+			"{name} {correlation}"
+		);
+	}
+}
+
+#[test]
+fn legacy_hash_quality() {
+	let noise = Noise {
+		float_mapping: FloatMapping::Portable,
+		..Noise::new(0)
+	};
+	check_hash(&noise, &GOOD_QUALITY);
+}
+
+/// A regression test, not a quality test: the cosine mapping is far from
+/// uniform (it favors the ends of [0, 1] like the arcsine distribution), and
+/// its bits are biased and correlated across channels. The bounds are just
+/// above what it measures (a chi-square of 396 798, a bit bias of 0.058 and
+/// a flip correlation of 0.169), so that any change to it is noticed.
+#[test]
+fn cos_mapping_regressions() {
+	let bounds = Bounds {
+		chi_square: 400_000.0,
+		flip_bias: 0.06,
+		flip_correlation: 0.17,
+	};
+	check_hash(&Noise::new(0), &bounds);
+}
+
+#[test]
+fn splitmix_hash_quality() {
+	let noise = Noise {
+		float_mapping: FloatMapping::Portable,
+		..Noise::new(0).with_hash(SplitMixHash)
+	};
+	check_hash(&noise, &GOOD_QUALITY);
+}
+
+/// A regression test, not a quality test: the permutation table only gives
+/// 256 different values, which cannot be uniform on 100 bins, and its bits
+/// are biased and correlated along y and the channels. The bounds are just
+/// above what it measures (a chi-square of 425 173, a bit bias of 0.070 and
+/// a flip correlation of 0.210), so that any change to it is noticed.
+#[test]
+fn permutation_hash_regressions() {
+	let noise = Noise {
+		float_mapping: FloatMapping::Portable,
+		..Noise::new(0).with_hash(PermutationHash)
+	};
+	let bounds = Bounds {
+		chi_square: 428_000.0,
+		flip_bias: 0.072,
+		flip_correlation: 0.212,
+	};
+	check_hash(&noise, &bounds);
+}
+
+#[test]
+fn channels_are_uncorrelated() {
+	for octave_count in [1, 4] {
+		let noise = Noise::new(0);
+		let first = octaves_samples(&noise, octave_count, 1);
+		let second = octaves_samples(&noise, octave_count, 2);
+		let correlation = correlation(&first, &second);
+		println!("correlation of channels [1] and [2] with {octave_count} octaves: {correlation:.4}");
+		assert!(correlation.abs() < 0.05, "{correlation}");
+	}
+}
+
+#[test]
+fn octaves_histograms() {
+	let noise = Noise::new(0);
+	for octave_count in 1..=8 {
+		let samples = octaves_samples(&noise, octave_count, 1);
+		let mut histogram = [0usize; 10];
+		for &value in &samples {
+			assert!((0.0..=1.0).contains(&value), "{value}");
+			histogram[((value * 10.0) as usize).min(9)] += 1;
+		}
+		let n = samples.len() as f64;
+		let mean = samples.iter().map(|&x| x as f64).sum::<f64>() / n;
+		let deviation = f64::sqrt(
+			samples
+				.iter()
+				.map(|&x| (x as f64 - mean).powi(2))
+				.sum::<f64>()
+				/ n,
+		);
+		let percents = histogram.map(|count| format!("{:4.1}", count as f64 * 100.0 / n));
+		println!(
+			"{octave_count} octaves: mean {mean:.3}, deviation {deviation:.3}, histogram (%) {}",
+			percents.join(" ")
+		);
+		assert!((mean - 0.5).abs() < 0.05, "{mean}");
+	}
+}
@@ -0,0 +1,148 @@
+//! Simplex lattice noise, an alternative to the hypercubic lattice of
+//! `raw_noise` that only touches D+1 nodes per sample instead of 2^D.
+//! The simplex lattice does not line up with the axes, so this noise
+//! does not tile even with `Noise::periods`.
+
+use crate::{
+	gradient_noise, lattice_gradient, raw_noise_with_gradient, Basis, Coord, Kernel, LatticeHash,
+	Noise,
+};
+
+/// Radius (squared) of the influence of a node.
+const RADIUS_SQUARED: f32 = 0.5;
+
+/// Factors that bring simplex noise to about [-1, 1], indexed by dimension
+/// (measured, as the extrema of the sums are not easy to get analytically).
+const SCALES: [f32; 5] = [0.0, 0.0, 96.0, 104.0, 104.0];
+
+/// Simplex noise in [0, 1], for D in 2, 3 and 4. Other dimensions
+/// fall back to gradient noise with the quintic kernel.
+pub fn simplex_noise<H: LatticeHash, C: Coord, const D: usize>(
+	noise: &Noise<H>,
+	xs: &[C; D],
+	channels: &[i32],
+) -> f32 {
+	simplex_noise_multi(noise, xs, &[channels])[0]
+}
+
+/// Same as `simplex_noise` for K channels at once,
+/// the simplex is only found once for all of them.
+pub fn simplex_noise_multi<H: LatticeHash, C: Coord, const D: usize, const K: usize>(
+	noise: &Noise<H>,
+	xs: &[C; D],
+	channels: &[&[i32]; K],
+) -> [f32; K] {
+	if !has_simplex_lattice::<D>() {
+		return gradient_noise(noise, Kernel::Quintic, xs, channels);
+	}
+	let mut values = [0.0; K];
+	for_each_simplex_corner(xs, |coords, corner_offsets, falloff| {
+		for (value, channels) in values.iter_mut().zip(channels) {
+			let gradient = lattice_gradient(noise, channels, coords);
+			let dot: f32 = gradient
+				.iter()
+				.zip(corner_offsets)
+				.map(|(g, offset)| g * offset)
+				.sum();
+			*value += falloff.powi(4) * dot;
+		}
+	});
+	values.map(|value| 0.5 + 0.5 * value * SCALES[D])
+}
+
+/// Same as `simplex_noise` but also gives the partial derivatives of the noise
+/// along each axis.
+pub fn simplex_noise_with_gradient<H: LatticeHash, C: Coord, const D: usize>(
+	noise: &Noise<H>,
+	xs: &[C; D],
+	channels: &[i32],
+) -> (f32, [f32; D]) {
+	if !has_simplex_lattice::<D>() {
+		let gradient_noise = Noise {
+			basis: Basis::Gradient { kernel: Kernel::Quintic },
+			..*noise
+		};
+		return raw_noise_with_gradient(&gradient_noise, xs, channels);
+	}
+	let mut value = 0.0;
+	let mut value_gradient = [0.0; D];
+	for_each_simplex_corner(xs, |coords, corner_offsets, falloff| {
+		let gradient = lattice_gradient(noise, channels, coords);
+		let dot: f32 = gradient
+			.iter()
+			.zip(corner_offsets)
+			.map(|(g, offset)| g * offset)
+			.sum();
+		value += falloff.powi(4) * dot;
+		// Derivative of falloff^4 * dot, knowing that the derivative
+		// of the offsets is the identity.
+		for axis in 0..D {
+			value_gradient[axis] +=
+				falloff.powi(4) * gradient[axis] - 8.0 * falloff.powi(3) * corner_offsets[axis] * dot;
+		}
+	});
+	value_gradient
+		.iter_mut()
+		.for_each(|component| *component *= 0.5 * SCALES[D]);
+	(0.5 + 0.5 * value * SCALES[D], value_gradient)
+}
+
+/// Whether simplex noise is available in D dimensions, `SCALES` has only
+/// been measured for these.
+fn has_simplex_lattice<const D: usize>() -> bool {
+	(2..=4).contains(&D)
+}
+
+/// Calls `corner` on the corners of the simplex that contains `xs` that are
+/// close enough to have an influence, with the lattice coordinates of the
+/// corner, the offset from the corner to `xs` and the falloff (positive).
+fn for_each_simplex_corner<C: Coord, const D: usize>(
+	xs: &[C; D],
+	mut corner: impl FnMut(&[i64; D], &[f32; D], f32),
+) {
+	debug_assert!(has_simplex_lattice::<D>());
+	// Skewing the space turns the simplices into a hypercubic lattice,
+	// in which finding the cell is easy. Far from the origin, the skewing
+	// factors need the precision of `C` for the cell to be the right one.
+	let n = C::from_i64(D as i64);
+	let one = C::from_f32(1.0);
+	let skew = (C::sqrt(n + one) - one) / n;
+	let unskew = (one - one / C::sqrt(n + one)) / n;
+	let skewed_sum = xs.iter().copied().sum::<C>() * skew;
+	let mut cell = [0; D];
+	for axis in 0..D {
+		cell[axis] = (xs[axis] + skewed_sum).split().0;
+	}
+	// The offsets are small, they are computed with the precision of `C`
+	// and then kept as `f32`.
+	let unskewed_sum = C::from_i64(cell.iter().copied().fold(0, i64::wrapping_add)) * unskew;
+	let unskew = unskew.to_f32();
+	let mut offsets = [0.0; D];
+	for axis in 0..D {
+		offsets[axis] = (xs[axis] - (C::from_i64(cell[axis]) - unskewed_sum)).to_f32();
+	}
+	// The simplex containing the point is found by going from the cell origin
+	// one axis at a time, in decreasing order of the offsets on these axes.
+	let mut axis_order = [0; D];
+	for (i, axis) in axis_order.iter_mut().enumerate() {
+		*axis = i;
+	}
+	axis_order.sort_by(|&a, &b| offsets[b].total_cmp(&offsets[a]));
+	let mut coords = cell;
+	for step in 0..=D {
+		if step > 0 {
+			let axis = axis_order[step - 1];
+			coords[axis] = coords[axis].wrapping_add(1);
+		}
+		let mut corner_offsets = offsets;
+		for axis in 0..D {
+			let node = coords[axis].wrapping_sub(cell[axis]);
+			corner_offsets[axis] += step as f32 * unskew - node as f32;
+		}
+		let distance_squared: f32 = corner_offsets.iter().map(|offset| offset * offset).sum();
+		let falloff = RADIUS_SQUARED - distance_squared;
+		if falloff > 0.0 {
+			corner(&coords, &corner_offsets, falloff);
+		}
+	}
+}
@@ -0,0 +1,118 @@
+//! Domain warping: sampling a noise function (or an image) at points moved
+//! by other noise functions.
+//!
+//! The displacement is either an offset per axis (`Offset`) or an angle and a
+//! distance (`Polar`), scaled by a strength that can vary from point to point.
+//! Since a `Warp` is itself a noise function, it can be warped again or be
+//! one of the fields of another warp.
+
+use crate::noise_fn::{NoiseFn, NoiseFnArray};
+use std::f32::consts::TAU;
+
+/// How far to move each point, before scaling by the strength of the warp.
+pub trait Displacement<const D: usize> {
+	fn offset(&self, xs: &[f32; D]) -> [f32; D];
+
+	/// The point moved by the full offset.
+	fn displace(&self, xs: &[f32; D]) -> [f32; D] {
+		let offset = self.offset(xs);
+		let mut xs = *xs;
+		xs.iter_mut()
+			.zip(offset)
+			.for_each(|(x, offset)| *x += offset);
+		xs
+	}
+}
+
+/// Moves each axis by the value of the field of that axis,
+/// mapped from [0, 1] to [-1, 1].
+#[derive(Clone, Copy, Debug)]
+pub struct Offset<F> {
+	pub fields: F,
+}
+
+impl<F: NoiseFnArray<D, D>, const D: usize> Displacement<D> for Offset<F> {
+	fn offset(&self, xs: &[f32; D]) -> [f32; D] {
+		self.fields.sample_all(xs).map(|field| field * 2.0 - 1.0)
+	}
+}
+
+/// Moves by `distance` in the direction given by `angle`,
+/// where an angle of 1 is a full turn.
+#[derive(Clone, Copy, Debug)]
+pub struct Polar<A, L> {
+	pub angle: A,
+	pub distance: L,
+}
+
+impl<A: NoiseFn<2>, L: NoiseFn<2>> Displacement<2> for Polar<A, L> {
+	fn offset(&self, xs: &[f32; 2]) -> [f32; 2] {
+		let angle = self.angle.sample(xs) * TAU;
+		let distance = self.distance.sample(xs);
+		[f32::cos(angle) * distance, f32::sin(angle) * distance]
+	}
+}
+
+/// See `NoiseFn::warp`, `NoiseFn::warp_polar` and `NoiseFn::warp_with`.
+#[derive(Clone, Copy, Debug)]
+pub struct Warp<N, W, S> {
+	pub(crate) source: N,
+	pub(crate) displacement: W,
+	pub(crate) strength: S,
+	pub(crate) samples: usize,
+}
+
+impl<N, W, S> Warp<N, W, S> {
+	pub fn new(source: N, displacement: W, strength: S) -> Warp<N, W, S> {
+		Warp { source, displacement, strength, samples: 1 }
+	}
+
+	/// Instead of sampling the source at the warped point only, averages
+	/// `samples` samples along the segment from the point to the warped point,
+	/// which smears the source along the warp.
+	pub fn line_integral(self, samples: usize) -> Warp<N, W, S> {
+		assert!(
+			samples >= 2,
+			"a line integral needs both ends of the segment"
+		);
+		Warp { samples, ..self }
+	}
+
+	/// Where the source is sampled for `xs` (the far end of the segment in the
+	/// case of a line integral).
+	pub fn warp_point<const D: usize>(&self, xs: &[f32; D]) -> [f32; D]
+	where
+		W: Displacement<D>,
+		S: NoiseFn<D>,
+	{
+		let strength = self.strength.sample(xs);
+		let offset = self.displacement.offset(xs);
+		let mut warped = *xs;
+		warped
+			.iter_mut()
+			.zip(offset)
+			.for_each(|(x, offset)| *x += offset * strength);
+		warped
+	}
+}
+
+impl<N: NoiseFn<D>, W: Displacement<D>, S: NoiseFn<D>, const D: usize> NoiseFn<D>
+	for Warp<N, W, S>
+{
+	fn sample(&self, xs: &[f32; D]) -> f32 {
+		let warped = self.warp_point(xs);
+		if self.samples == 1 {
+			return self.source.sample(&warped);
+		}
+		let mut value = 0.0;
+		for i in 0..self.samples {
+			let ratio = i as f32 / (self.samples - 1) as f32;
+			let mut point = [0.0; D];
+			for (axis, x) in point.iter_mut().enumerate() {
+				*x = xs[axis] * (1.0 - ratio) + warped[axis] * ratio;
+			}
+			value += self.source.sample(&point);
+		}
+		value / self.samples as f32
+	}
+}
@@ -0,0 +1,184 @@
+//! Watershed segmentation of a heightfield sampled on a grid: every pixel is
+//! labelled by the pit (local minimum) that water flowing down from it ends
+//! in, so that the regions are the catchment basins of the heightfield and
+//! their boundaries are its ridgelines.
+//!
+//! Water flows from each pixel to the neighbor (out of 8) with the steepest
+//! descent, the pits being the pixels with no lower neighbor. Flat areas are
+//! not drained, each of their pixels is a pit of its own, which is never a
+//! problem with noise but can be one with clamped values.
+
+use crate::heightfield::Heightfield;
+use std::fmt::Write;
+
+/// The neighbor that water flows to from a pixel, none for a pit.
+fn downhill(field: &Heightfield, index: usize) -> Option<usize> {
+	let height = field.heights[index];
+	let mut steepest = None;
+	let mut steepest_slope = 0.0;
+	for (neighbor, distance) in field.neighbors(index) {
+		let slope = (height - field.heights[neighbor]) / distance;
+		if slope > steepest_slope {
+			(steepest, steepest_slope) = (Some(neighbor), slope);
+		}
+	}
+	steepest
+}
+
+/// A catchment basin, see `Segmentation::basins`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Basin {
+	/// The pixel that the basin drains to.
+	pub pit: (u32, u32),
+	pub pit_height: f32,
+	/// In pixels.
+	pub area: usize,
+	pub mean_height: f32,
+	/// The height that water has to reach in the basin to spill over into
+	/// a neighboring basin, the height of the lowest pass of its ridgeline.
+	/// None when the basin is the only one.
+	pub pour_height: Option<f32>,
+	/// Whether the basin reaches the edge of the grid,
+	/// beyond which it could drain somewhere else.
+	pub touches_edge: bool,
+}
+
+/// The result of `watershed`.
+#[derive(Clone, Debug)]
+pub struct Segmentation {
+	pub side: u32,
+	/// The label of every pixel, row after row. Labels are indices into
+	/// `basins`, numbered in the order of the pits row after row.
+	pub labels: Vec<usize>,
+	pub basins: Vec<Basin>,
+}
+
+/// Labels every pixel of `field` with its catchment basin.
+pub fn watershed(field: &Heightfield) -> Segmentation {
+	const UNLABELLED: usize = usize::MAX;
+	let side = field.side;
+	let mut labels = vec![UNLABELLED; field.heights.len()];
+	let mut basins = Vec::new();
+	let mut path = Vec::new();
+	for start in 0..labels.len() {
+		// Flows down until a labelled pixel or a pit, then labels the way.
+		let mut index = start;
+		while labels[index] == UNLABELLED {
+			path.push(index);
+			match downhill(field, index) {
+				Some(next) => index = next,
+				None => {
+					labels[index] = basins.len();
+					basins.push(Basin {
+						pit: (index as u32 % side, index as u32 / side),
+						pit_height: field.heights[index],
+						area: 0,
+						mean_height: 0.0,
+						pour_height: None,
+						touches_edge: false,
+					});
+				},
+			}
+		}
+		let label = labels[index];
+		for index in path.drain(..) {
+			labels[index] = label;
+		}
+	}
+
+	let mut height_sums = vec![0.0f64; basins.len()];
+	for (index, &label) in labels.iter().enumerate() {
+		let (x, y) = (index as u32 % side, index as u32 / side);
+		let basin = &mut basins[label];
+		basin.area += 1;
+		height_sums[label] += field.heights[index] as f64;
+		if x == 0 || y == 0 || x == side - 1 || y == side - 1 {
+			basin.touches_edge = true;
+		}
+		// The water of the higher pixel of a pair across a ridgeline
+		// passes between the two basins.
+		for (neighbor, _distance) in field.neighbors(index) {
+			let neighbor_label = labels[neighbor];
+			if neighbor_label != label {
+				let pass = f32::max(field.heights[index], field.heights[neighbor]);
+				let pour_height = basin.pour_height.get_or_insert(pass);
+				*pour_height = f32::min(*pour_height, pass);
+			}
+		}
+	}
+	for (basin, height_sum) in basins.iter_mut().zip(height_sums) {
+		basin.mean_height = (height_sum / basin.area as f64) as f32;
+	}
+	Segmentation { side, labels, basins }
+}
+
+/// The colors of the generators that segment the plane (from 43 on),
+/// different labels get colors that look unrelated.
+pub fn label_color(label: usize) -> image::Rgb<u8> {
+	image::Rgb([
+		((label * 1827 + 237) % 256) as u8,
+		((label * 1911 + 141) % 256) as u8,
+		((label * 1137 + 883) % 256) as u8,
+	])
+}
+
+impl Segmentation {
+	pub fn label(&self, x: u32, y: u32) -> usize {
+		self.labels[(y * self.side + x) as usize]
+	}
+
+	/// Whether a pixel is on the boundary of its basin,
+	/// next to a pixel (out of 4) of another basin.
+	pub fn is_ridge(&self, x: u32, y: u32) -> bool {
+		let label = self.label(x, y);
+		let neighbors = [
+			(x.wrapping_sub(1), y),
+			(x + 1, y),
+			(x, y.wrapping_sub(1)),
+			(x, y + 1),
+		];
+		neighbors
+			.into_iter()
+			.any(|(x, y)| x < self.side && y < self.side && self.label(x, y) != label)
+	}
+
+	/// The basins in their `label_color`, with the ridgelines in black.
+	pub fn label_image(&self) -> image::RgbImage {
+		image::ImageBuffer::from_fn(self.side, self.side, |x, y| {
+			if self.is_ridge(x, y) {
+				image::Rgb([0, 0, 0])
+			} else {
+				label_color(self.label(x, y))
+			}
+		})
+	}
+
+	/// The basins as a text table, one line per basin.
+	pub fn stats_table(&self) -> String {
+		let mut table = String::new();
+		writeln!(
+			table,
+			"{:>6} {:>5} {:>5} {:>8} {:>8} {:>8} {:>8} {:>4}",
+			"label", "pit_x", "pit_y", "pit", "area", "mean", "pour", "edge"
+		)
+		.unwrap();
+		for (label, basin) in self.basins.iter().enumerate() {
+			let pour_height = match basin.pour_height {
+				Some(pour_height) => format!("{pour_height:.4}"),
+				None => "-".to_string(),
+			};
+			writeln!(
+				table,
+				"{label:>6} {:>5} {:>5} {:>8.4} {:>8} {:>8.4} {pour_height:>8} {:>4}",
+				basin.pit.0,
+				basin.pit.1,
+				basin.pit_height,
+				basin.area,
+				basin.mean_height,
+				if basin.touches_edge { "yes" } else { "no" },
+			)
+			.unwrap();
+		}
+		table
+	}
+}